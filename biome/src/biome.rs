@@ -0,0 +1,174 @@
+//! Biomes of a map, from a Whittaker-like table of temperature and moisture ranges.
+use climate::Climate;
+use heightmap::{Heightmap, Sample, heightmap_from_iter};
+use image::{Color, GrayImage, Luma, Rgb, RgbImage};
+use range::Range;
+use serde::{Deserialize, Deserializer};
+use serde::de::Error;
+use std::f64;
+use temperature::Temperature;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Biome {
+    pub name: String,
+    pub color: Color,
+    /// Whether the biome is under the sea level.
+    #[serde(default)]
+    pub water: bool,
+    /// In °C.
+    #[serde(default)]
+    pub temperature: Range,
+    /// From `0` for the driest to `1` for the wettest.
+    #[serde(default)]
+    pub moisture: Range,
+}
+
+impl Biome {
+    fn new(name: &str, rgb: [u8; 3], water: bool, temperature: Range, moisture: Range) -> Biome {
+        Biome {
+            name: name.to_string(),
+            color: Color::new(rgb),
+            water,
+            temperature,
+            moisture,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BiomeOptions {
+    #[serde(default)]
+    pub temperature: Temperature,
+    /// Scale of the noise used as moisture without a climate.
+    #[serde(default = "default_moisture_scale")]
+    pub moisture_scale: f64,
+    /// Simulated climate giving the temperature and the moisture.
+    #[serde(default)]
+    pub climate: Option<Climate>,
+    /// A sample belongs to the first biome matching it, `255` biomes at most.
+    #[serde(default = "default_biomes", deserialize_with = "deserialize_biomes")]
+    pub biomes: Vec<Biome>,
+}
+
+fn default_moisture_scale() -> f64 {
+    2.0
+}
+
+fn default_biomes() -> Vec<Biome> {
+    let all = Range::default();
+    let (min, max) = (f64::NEG_INFINITY, f64::INFINITY);
+
+    vec![
+        Biome::new("sea ice", [220, 230, 240], true, Range::new(min, -5.0), all),
+        Biome::new("ocean", [17, 82, 112], true, all, all),
+        Biome::new("ice", [245, 245, 250], false, Range::new(min, -10.0), all),
+        Biome::new("tundra", [163, 163, 124], false, Range::new(-10.0, 0.0), all),
+        Biome::new("taiga", [60, 100, 70], false, Range::new(0.0, 8.0), Range::new(0.4, max)),
+        Biome::new("cold steppe", [160, 150, 110], false, Range::new(0.0, 8.0), all),
+        Biome::new("temperate rainforest", [40, 110, 60], false, Range::new(8.0, 20.0), Range::new(0.75, max)),
+        Biome::new("temperate forest", [80, 130, 60], false, Range::new(8.0, 20.0), Range::new(0.4, max)),
+        Biome::new("grassland", [160, 170, 90], false, Range::new(8.0, 20.0), all),
+        Biome::new("tropical rainforest", [20, 100, 30], false, Range::new(20.0, max), Range::new(0.7, max)),
+        Biome::new("savanna", [170, 160, 70], false, Range::new(20.0, max), Range::new(0.3, max)),
+        Biome::new("desert", [220, 200, 140], false, Range::new(20.0, max), all),
+    ]
+}
+
+/// Biomes whose ids fit in the samples of a `BiomeMap`, the last id being kept for `NONE`.
+fn deserialize_biomes<D>(deserializer: D) -> Result<Vec<Biome>, D::Error>
+    where D: Deserializer
+{
+    let biomes = Vec::<Biome>::deserialize(deserializer)?;
+    if biomes.len() > BiomeMap::NONE as usize {
+        return Err(D::Error::custom(format!("at most {} biomes", BiomeMap::NONE)));
+    }
+
+    Ok(biomes)
+}
+
+impl Default for BiomeOptions {
+    fn default() -> BiomeOptions {
+        BiomeOptions {
+            temperature: Temperature::default(),
+            moisture_scale: default_moisture_scale(),
+            climate: None,
+            biomes: default_biomes(),
+        }
+    }
+}
+
+impl BiomeOptions {
+    /// Index of the first biome matching a sample.
+    pub fn classify(&self, water: bool, temperature: f64, moisture: f64) -> Option<usize> {
+        self.biomes
+            .iter()
+            .position(|b| b.water == water && b.temperature.contains(temperature) && b.moisture.contains(moisture))
+    }
+
+    /// Biome of each sample of the map, the water lying below `sea_level`, `temperature` being in °C and
+    /// `moisture` in `[0, 1]`.
+    pub fn classify_map<T>(&self,
+                           hmap: &Heightmap<T>,
+                           sea_level: f64,
+                           temperature: &Heightmap,
+                           moisture: &Heightmap)
+                           -> BiomeMap
+        where T: Sample
+    {
+        let (width, height) = (hmap.width(), hmap.height());
+        let ids = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                     self.classify(hmap.get(x, y) < sea_level, temperature.get(x, y), moisture.get(x, y))
+                         .map_or(BiomeMap::NONE, |i| i as u8)
+                 })
+            .collect();
+
+        BiomeMap { width, height, ids }
+    }
+}
+
+/// Biome of each sample, as an index in the list of biomes.
+pub struct BiomeMap {
+    width: u32,
+    height: u32,
+    ids: Vec<u8>,
+}
+
+impl BiomeMap {
+    /// Id of the samples matching no biome.
+    pub const NONE: u8 = u8::MAX;
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn id(&self, x: u32, y: u32) -> u8 {
+        self.ids[(y * self.width + x) as usize]
+    }
+
+    /// Grayscale image whose values are the ids.
+    pub fn ids_image(&self) -> GrayImage {
+        GrayImage::from_fn(self.width, self.height, |x, y| Luma([self.id(x, y)]))
+    }
+
+    /// `1` on the samples whose biome is named in `names`, `0` elsewhere.
+    pub fn mask(&self, biomes: &[Biome], names: &[String]) -> Heightmap {
+        let matches: Vec<bool> = biomes.iter().map(|b| names.iter().any(|name| *name == b.name)).collect();
+        let mask = self.ids.iter().map(|&id| if *matches.get(id as usize).unwrap_or(&false) { 1.0 } else { 0.0 });
+        heightmap_from_iter(self.width, self.height, mask)
+    }
+
+    /// Colour of the biome of each sample, black where no biome matches.
+    pub fn colorize(&self, biomes: &[Biome]) -> RgbImage {
+        RgbImage::from_fn(self.width, self.height, |x, y| {
+            biomes
+                .get(self.id(x, y) as usize)
+                .map_or(Rgb([0, 0, 0]), |b| *b.color)
+        })
+    }
+}
@@ -0,0 +1,219 @@
+//! Precipitation carried by prevailing winds.
+//!
+//! Air crossing the map picks moisture up over water, loses a part of it as rain on its way, more
+//! when it rises over the terrain and all it cannot hold once cooled, leaving a rain shadow behind
+//! mountains. Rates are given as distances in map widths, so that the climate does not depend on
+//! the resolution of the map.
+use heightmap::{Heightmap, Sample, heightmap_from_vec};
+use range::Range;
+use std::f64;
+use temperature::Temperature;
+
+/// Wind blowing over the rows of a latitude band.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Wind {
+    /// In degrees.
+    pub latitude: Range,
+    /// Bearing the wind blows towards, in degrees clockwise from the top of the map.
+    pub direction: f64,
+}
+
+impl Wind {
+    fn new(min: f64, max: f64, direction: f64) -> Wind {
+        Wind {
+            latitude: Range::new(min, max),
+            direction,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Climate {
+    /// A row gets the wind of the first band containing its latitude, the first one if none does.
+    #[serde(default = "default_winds")]
+    pub winds: Vec<Wind>,
+    /// Distance over water in which air picks up about two thirds of the moisture it lacks.
+    #[serde(default = "default_evaporation")]
+    pub evaporation: f64,
+    /// Distance over flat ground in which air loses about two thirds of its moisture.
+    #[serde(default = "default_rain")]
+    pub rain: f64,
+    /// Share of its moisture air loses when rising by a height of `1.0` is `1 - e^-uplift`.
+    #[serde(default = "default_uplift")]
+    pub uplift: f64,
+    /// Moisture of the air entering the map, as a share of what it can hold.
+    #[serde(default = "default_inflow")]
+    pub inflow: f64,
+}
+
+/// Trade winds, westerlies and polar easterlies.
+fn default_winds() -> Vec<Wind> {
+    vec![
+        Wind::new(60.0, 90.0, 225.0),
+        Wind::new(30.0, 60.0, 45.0),
+        Wind::new(0.0, 30.0, 225.0),
+        Wind::new(-30.0, 0.0, 315.0),
+        Wind::new(-60.0, -30.0, 135.0),
+        Wind::new(-90.0, -60.0, 315.0),
+    ]
+}
+
+fn default_evaporation() -> f64 {
+    0.05
+}
+
+fn default_rain() -> f64 {
+    0.5
+}
+
+fn default_uplift() -> f64 {
+    10.0
+}
+
+fn default_inflow() -> f64 {
+    0.5
+}
+
+impl Default for Climate {
+    fn default() -> Climate {
+        Climate {
+            winds: default_winds(),
+            evaporation: default_evaporation(),
+            rain: default_rain(),
+            uplift: default_uplift(),
+            inflow: default_inflow(),
+        }
+    }
+}
+
+/// Rasters of a simulated climate.
+pub struct ClimateMaps {
+    /// In °C.
+    pub temperature: Heightmap,
+    /// Rain of each sample, `1.0` being the rain of saturated air at 30 °C on flat ground.
+    pub precipitation: Heightmap,
+    /// Precipitation relative to what air can hold at the temperature of the sample, from `0` for
+    /// the driest to about `1` for the wettest.
+    pub moisture: Heightmap,
+}
+
+/// Moisture air can hold at a temperature in °C, `1.0` at 30 °C.
+fn capacity(temperature: f64) -> f64 {
+    (0.07 * (temperature - 30.0)).exp()
+}
+
+/// Share lost over a distance, for a decay of about two thirds over `length`.
+fn rate(distance: f64, length: f64) -> f64 {
+    if length > 0.0 {
+        1.0 - (-distance / length).exp()
+    } else {
+        1.0
+    }
+}
+
+/// Value between four samples, given as the top left and bottom right ones and the position.
+fn bilinear<F>(v: F, ((x0, y0), (x1, y1), (fx, fy)): ((usize, usize), (usize, usize), (f64, f64))) -> f64
+    where F: Fn(usize, usize) -> f64
+{
+    (v(x0, y0) * (1.0 - fx) + v(x1, y0) * fx) * (1.0 - fy) + (v(x0, y1) * (1.0 - fx) + v(x1, y1) * fx) * fy
+}
+
+impl Climate {
+    /// Wind of a latitude, as a step whose largest component is one sample.
+    fn step(&self, latitude: f64) -> (f64, f64) {
+        let direction = self.winds
+            .iter()
+            .find(|w| w.latitude.contains(latitude))
+            .or_else(|| self.winds.first())
+            .map_or(90.0, |w| w.direction)
+            .to_radians();
+        let (dx, dy) = (direction.sin(), -direction.cos());
+        let n = dx.abs().max(dy.abs());
+
+        (dx / n, dy / n)
+    }
+
+    /// Simulates the climate of a map whose seas are below `sea_level`.
+    pub fn simulate<T>(&self, hmap: &Heightmap<T>, sea_level: f64, temperature: &Temperature) -> ClimateMaps
+        where T: Sample
+    {
+        let (width, height) = (hmap.width() as usize, hmap.height() as usize);
+        let temperatures = temperature.raster(hmap, sea_level);
+        let winds: Vec<_> = (0..height)
+            .map(|y| self.step(temperature.latitude(y as u32, height as u32)))
+            .collect();
+
+        // Where the winds of two rows blow towards each other, air would go back and forth between
+        // them: it comes along the row instead
+        let steps: Vec<_> = (0..height)
+            .map(|y| {
+                let (sx, sy) = winds[y];
+                let upwind = if sy > 0.0 { y.checked_sub(1) } else if sy < 0.0 { Some(y + 1) } else { None };
+                match upwind.and_then(|u| winds.get(u)) {
+                    Some(&(_, uy)) if uy * sy < 0.0 => (sx.signum(), 0.0),
+                    _ => (sx, sy),
+                }
+            })
+            .collect();
+        let level = |x: usize, y: usize| hmap.get(x as u32, y as u32).max(sea_level);
+        let sample = 1.0 / width as f64;
+
+        // Moisture of the air leaving each sample, updated downwind until it settles: rows are swept
+        // in both directions in turn, so that a few passes follow winds changing with the latitude
+        let mut air = vec![0.0; width * height];
+        let mut rain = vec![0.0; width * height];
+        for pass in 0..16 {
+            let mut change = 0.0f64;
+            for j in 0..height {
+                let y = if pass % 2 == 0 { j } else { height - 1 - j };
+                let (sx, sy) = steps[y];
+                let distance = sx.hypot(sy) * sample;
+                for i in 0..width {
+                    let x = if sx >= 0.0 { i } else { width - 1 - i };
+                    let t = temperatures.get(x as u32, y as u32);
+                    let cap = capacity(t);
+
+                    // Air comes from between two samples of the previous column or row
+                    let (ux, uy) = (x as f64 - sx, y as f64 - sy);
+                    let (mut q, rise) = if ux < 0.0 || uy < 0.0 || ux > (width - 1) as f64 ||
+                                           uy > (height - 1) as f64 {
+                        (self.inflow * cap, 0.0)
+                    } else {
+                        let (x0, y0) = (ux.floor() as usize, uy.floor() as usize);
+                        let (x1, y1) = (ux.ceil() as usize, uy.ceil() as usize);
+                        let (fx, fy) = (ux - x0 as f64, uy - y0 as f64);
+                        let corners = ((x0, y0), (x1, y1), (fx, fy));
+                        (bilinear(|x, y| air[y * width + x], corners), level(x, y) - bilinear(&level, corners))
+                    };
+
+                    if hmap.get(x as u32, y as u32) < sea_level {
+                        q += (cap - q).max(0.0) * rate(distance, self.evaporation);
+                    }
+                    let mut r = q * rate(distance, self.rain);
+                    r += (q - r) * (1.0 - (-self.uplift * rise.max(0.0)).exp());
+                    r = r.max(q - cap);
+
+                    let index = y * width + x;
+                    change = change.max((air[index] - (q - r)).abs());
+                    air[index] = q - r;
+                    rain[index] = r / rate(distance, self.rain);
+                }
+            }
+
+            if change < 1e-6 {
+                break;
+            }
+        }
+
+        let moisture = rain.iter()
+            .zip(temperatures.heights())
+            .map(|(p, &t)| (p / capacity(t)).min(1.0))
+            .collect();
+
+        ClimateMaps {
+            temperature: temperatures,
+            precipitation: heightmap_from_vec(width as u32, height as u32, rain),
+            moisture: heightmap_from_vec(width as u32, height as u32, moisture),
+        }
+    }
+}
@@ -0,0 +1,15 @@
+extern crate heightmap;
+extern crate image;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+mod biome;
+mod climate;
+mod range;
+mod temperature;
+
+pub use biome::{Biome, BiomeMap, BiomeOptions};
+pub use climate::{Climate, ClimateMaps, Wind};
+pub use range::Range;
+pub use temperature::Temperature;
@@ -0,0 +1,53 @@
+//! Interval of values, written `"min max"` with `inf` and `-inf` for unbounded sides.
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::Error;
+use std::f64;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Range {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Range {
+    pub fn new(min: f64, max: f64) -> Range {
+        Range { min, max }
+    }
+
+    /// Includes `min`, excludes `max`.
+    pub fn contains(&self, value: f64) -> bool {
+        self.min <= value && value < self.max
+    }
+}
+
+impl Default for Range {
+    fn default() -> Range {
+        Range::new(f64::NEG_INFINITY, f64::INFINITY)
+    }
+}
+
+impl Serialize for Range {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.collect_str(&format_args!("{} {}", self.min, self.max))
+    }
+}
+
+impl Deserialize for Range {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer
+    {
+        let string = String::deserialize(deserializer)?;
+        let mut iter = string.split_whitespace();
+
+        let min = iter.next()
+            .map(|s| s.parse().map_err(D::Error::custom))
+            .unwrap_or_else(||Err(D::Error::custom("missing value")))?;
+        let max = iter.next()
+            .map(|s| s.parse().map_err(D::Error::custom))
+            .unwrap_or_else(||Err(D::Error::custom("missing value")))?;
+
+        Ok(Range::new(min, max))
+    }
+}
@@ -0,0 +1,86 @@
+//! Temperature from the latitude and the elevation.
+use heightmap::{Heightmap, Sample, heightmap_from_iter};
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Temperature {
+    /// Temperature at the sea level on the equator, in °C.
+    #[serde(default = "default_equator")]
+    pub equator: f64,
+    /// Temperature at the sea level on the poles, in °C.
+    #[serde(default = "default_pole")]
+    pub pole: f64,
+    /// Latitude of the top row of the map, in degrees.
+    #[serde(default = "default_north")]
+    pub north: f64,
+    /// Latitude of the bottom row of the map, in degrees.
+    #[serde(default = "default_south")]
+    pub south: f64,
+    /// Temperature drop from the sea level to a height of `1.0`, in °C.
+    #[serde(default = "default_lapse")]
+    pub lapse: f64,
+}
+
+fn default_equator() -> f64 {
+    30.0
+}
+
+fn default_pole() -> f64 {
+    -25.0
+}
+
+fn default_north() -> f64 {
+    90.0
+}
+
+fn default_south() -> f64 {
+    -90.0
+}
+
+fn default_lapse() -> f64 {
+    40.0
+}
+
+impl Default for Temperature {
+    fn default() -> Temperature {
+        Temperature {
+            equator: default_equator(),
+            pole: default_pole(),
+            north: default_north(),
+            south: default_south(),
+            lapse: default_lapse(),
+        }
+    }
+}
+
+impl Temperature {
+    /// Latitude of a row of a map `height` samples high, in degrees.
+    pub fn latitude(&self, y: u32, height: u32) -> f64 {
+        let t = if height > 1 {
+            f64::from(y) / f64::from(height - 1)
+        } else {
+            0.5
+        };
+
+        self.north + (self.south - self.north) * t
+    }
+
+    /// Temperature at a latitude and a height, nothing being colder than the sea level under water.
+    pub fn at(&self, latitude: f64, height: f64, sea_level: f64) -> f64 {
+        let sea = self.pole + (self.equator - self.pole) * latitude.to_radians().cos();
+        let above = (height - sea_level).max(0.0) / (1.0 - sea_level).max(::std::f64::EPSILON);
+
+        sea - self.lapse * above
+    }
+
+    /// Temperature of each sample of the map.
+    pub fn raster<T>(&self, hmap: &Heightmap<T>, sea_level: f64) -> Heightmap
+        where T: Sample
+    {
+        let (width, height) = (hmap.width(), hmap.height());
+        let temperatures = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| self.at(self.latitude(y, height), hmap.get(x, y), sea_level));
+
+        heightmap_from_iter(width, height, temperatures)
+    }
+}
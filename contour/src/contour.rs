@@ -0,0 +1,132 @@
+//! Topographic contours of a map.
+use heightmap::{Contour, Heightmap, Sample};
+use image::Color;
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::Error;
+
+/// File the contours are exported to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContourFormat {
+    GeoJson,
+    Svg,
+}
+
+impl ContourFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ContourFormat::GeoJson => "geojson",
+            ContourFormat::Svg => "svg",
+        }
+    }
+}
+
+impl Serialize for ContourFormat {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_str(self.extension())
+    }
+}
+
+impl Deserialize for ContourFormat {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer
+    {
+        let string = String::deserialize(deserializer)?;
+        match string.as_str() {
+            "geojson" => Ok(ContourFormat::GeoJson),
+            "svg" => Ok(ContourFormat::Svg),
+            s => Err(D::Error::unknown_variant(s, &["geojson", "svg"])),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ContourOptions {
+    /// Height between two contours.
+    #[serde(default = "default_interval")]
+    pub interval: f64,
+    /// Height of the coastline, the other contours being at multiples of `interval` from it.
+    #[serde(default = "default_base")]
+    pub base: f64,
+    /// Every `index`th contour from the coastline is an index contour, drawn thicker and labelled in
+    /// SVG files, `0` for none.
+    #[serde(default = "default_index")]
+    pub index: u32,
+    /// Whether contours below the coastline are traced too.
+    #[serde(default)]
+    pub underwater: bool,
+    #[serde(default = "default_color")]
+    pub color: Color,
+    /// In pixels.
+    #[serde(default = "default_width")]
+    pub width: f64,
+    #[serde(default = "default_index_width")]
+    pub index_width: f64,
+    /// Written along the map when set.
+    #[serde(default)]
+    pub export: Option<ContourFormat>,
+    /// Whether contours are drawn on the rendered map.
+    #[serde(default = "default_draw")]
+    pub draw: bool,
+}
+
+fn default_interval() -> f64 {
+    0.025
+}
+
+fn default_base() -> f64 {
+    0.5
+}
+
+fn default_index() -> u32 {
+    5
+}
+
+fn default_color() -> Color {
+    Color::new([0x6Bu8, 0x44u8, 0x23u8])
+}
+
+fn default_width() -> f64 {
+    1.0
+}
+
+fn default_index_width() -> f64 {
+    2.0
+}
+
+fn default_draw() -> bool {
+    true
+}
+
+impl Default for ContourOptions {
+    fn default() -> ContourOptions {
+        ContourOptions {
+            interval: default_interval(),
+            base: default_base(),
+            index: default_index(),
+            underwater: false,
+            color: default_color(),
+            width: default_width(),
+            index_width: default_index_width(),
+            export: None,
+            draw: default_draw(),
+        }
+    }
+}
+
+impl ContourOptions {
+    /// Contours of a map, from the lowest to the highest.
+    pub fn trace<T>(&self, hmap: &Heightmap<T>) -> Vec<Contour>
+        where T: Sample
+    {
+        hmap.contours(self.base, self.interval)
+            .into_iter()
+            .filter(|c| self.underwater || c.level >= self.base)
+            .collect()
+    }
+
+    pub fn is_index(&self, level: f64) -> bool {
+        self.index > 0 && ((level - self.base) / self.interval).round() as i64 % i64::from(self.index) == 0
+    }
+}
@@ -0,0 +1,16 @@
+//! Anti-aliased contours drawn over a rendered map.
+use contour::ContourOptions;
+use heightmap::{Contour, heightmap_from_vec};
+use image::{RgbImage, overlay, trace_line};
+
+/// Draws the contours, index contours with their own width.
+pub fn draw_contours(img: &mut RgbImage, contours: &[Contour], options: &ContourOptions) {
+    let (width, height) = img.dimensions();
+    let mut coverage = heightmap_from_vec(width, height, vec![0.0; width as usize * height as usize]);
+    for contour in contours {
+        let size = if options.is_index(contour.level) { options.index_width } else { options.width };
+        trace_line(&mut coverage, &contour.points, contour.closed, size);
+    }
+
+    overlay(img, &coverage, options.color, 1.0);
+}
@@ -0,0 +1,114 @@
+//! Contours written as polylines for GIS and drawing tools.
+use contour::ContourOptions;
+use heightmap::Contour;
+use image::{Color, GeoReference};
+use serde_json;
+use std::io;
+use std::io::Write;
+
+/// Writes the contours as lines, in the coordinates of `georef` for a map `rows` samples high, with
+/// their elevation.
+pub fn write_geojson<W: Write>(mut w: W,
+                               contours: &[Contour],
+                               options: &ContourOptions,
+                               georef: &GeoReference,
+                               rows: u32)
+                               -> io::Result<()> {
+    let features: Vec<_> = contours.iter()
+        .map(|contour| {
+            let mut coordinates: Vec<_> = contour.points
+                .iter()
+                .map(|&(x, y)| {
+                    let (x, y) = georef.position(x, y, rows);
+                    json!([x, y])
+                })
+                .collect();
+            if contour.closed {
+                let first = coordinates[0].clone();
+                coordinates.push(first);
+            }
+
+            json!({
+                "type": "Feature",
+                "geometry": {"type": "LineString", "coordinates": coordinates},
+                "properties": {
+                    "elevation": georef.elevation(contour.level),
+                    "index": options.is_index(contour.level),
+                },
+            })
+        })
+        .collect();
+
+    let collection = json!({"type": "FeatureCollection", "features": features});
+    serde_json::to_writer_pretty(&mut w, &collection).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+    w.flush()
+}
+
+/// Writes the contours as polylines over a map of `width` by `height` pixels, index contours being
+/// labelled with their elevation.
+pub fn write_svg<W: Write>(mut w: W,
+                           contours: &[Contour],
+                           options: &ContourOptions,
+                           georef: &GeoReference,
+                           (width, height): (u32, u32))
+                           -> io::Result<()> {
+    writeln!(w,
+             r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+             width,
+             height,
+             width,
+             height)?;
+    svg_lines(&mut w, contours, options)?;
+    svg_labels(&mut w, contours, options, georef)?;
+    writeln!(w, "</svg>")?;
+
+    w.flush()
+}
+
+/// Color in SVG attributes.
+pub fn svg_color(color: Color) -> String {
+    format!("rgb({},{},{})", color.red(), color.green(), color.blue())
+}
+
+/// Group of the contour lines.
+pub fn svg_lines<W: Write>(w: &mut W, contours: &[Contour], options: &ContourOptions) -> io::Result<()> {
+    writeln!(w,
+             r#"<g fill="none" stroke="{}" stroke-linejoin="round" stroke-linecap="round">"#,
+             svg_color(options.color))?;
+    for contour in contours {
+        let size = if options.is_index(contour.level) { options.index_width } else { options.width };
+        let points: Vec<_> = contour.points.iter().map(|&(x, y)| format!("{:.2},{:.2}", x, y)).collect();
+        writeln!(w,
+                 r#"<{} points="{}" stroke-width="{}"/>"#,
+                 if contour.closed { "polygon" } else { "polyline" },
+                 points.join(" "),
+                 size)?;
+    }
+
+    writeln!(w, "</g>")
+}
+
+/// Group of the elevations at the middle of the index contours long enough to hold them, over a halo
+/// hiding the line.
+pub fn svg_labels<W: Write>(w: &mut W,
+                            contours: &[Contour],
+                            options: &ContourOptions,
+                            georef: &GeoReference)
+                            -> io::Result<()> {
+    writeln!(w,
+             r#"<g font-family="serif" font-size="10" text-anchor="middle" dominant-baseline="middle" fill="{}""#,
+             svg_color(options.color))?;
+    writeln!(w, r#"   stroke="white" stroke-width="3" paint-order="stroke">"#)?;
+    let length = |c: &Contour| c.points.windows(2).map(|s| (s[1].0 - s[0].0).hypot(s[1].1 - s[0].1)).sum::<f64>();
+    for contour in contours.iter().filter(|c| options.is_index(c.level) && length(c) > 50.0) {
+        let (x, y) = contour.points[contour.points.len() / 2];
+        writeln!(w,
+                 r#"<text x="{:.2}" y="{:.2}">{}</text>"#,
+                 x,
+                 y,
+                 georef.elevation(contour.level).round())?;
+    }
+
+    writeln!(w, "</g>")
+}
@@ -0,0 +1,158 @@
+//! Islands, continents, seas and lakes of a map, exported as polygons and checked against
+//! constraints to reject seeds.
+use heightmap::{Component, Components, Heightmap, Sample};
+use image::GeoReference;
+use serde_json;
+use std::io;
+use std::io::Write;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LandmassOptions {
+    /// Largest distance in samples between a simplified outline and the traced one.
+    #[serde(default = "default_tolerance")]
+    pub tolerance: f64,
+    /// Whether the components are written along the map.
+    #[serde(default = "default_export")]
+    pub export: bool,
+    /// Fewest islands, land not reaching the borders of the map.
+    #[serde(default)]
+    pub min_islands: Option<usize>,
+    #[serde(default)]
+    pub max_islands: Option<usize>,
+    /// Smallest area of the largest island, as a share of the map.
+    #[serde(default)]
+    pub min_island_area: Option<f64>,
+    /// Bounds of the share of the map above the sea level.
+    #[serde(default)]
+    pub min_land: Option<f64>,
+    #[serde(default)]
+    pub max_land: Option<f64>,
+    /// Seeds tried, one after the other, until one meets the constraints.
+    #[serde(default = "default_attempts")]
+    pub attempts: usize,
+}
+
+fn default_tolerance() -> f64 {
+    0.5
+}
+
+fn default_export() -> bool {
+    true
+}
+
+fn default_attempts() -> usize {
+    100
+}
+
+impl Default for LandmassOptions {
+    fn default() -> LandmassOptions {
+        LandmassOptions {
+            tolerance: default_tolerance(),
+            export: default_export(),
+            min_islands: None,
+            max_islands: None,
+            min_island_area: None,
+            min_land: None,
+            max_land: None,
+            attempts: default_attempts(),
+        }
+    }
+}
+
+impl LandmassOptions {
+    /// Traces the outlines of the components of the map and simplifies them, the ones of a few
+    /// samples being kept as they are.
+    pub fn trace<T>(&self, hmap: &Heightmap<T>, components: &mut Components)
+        where T: Sample
+    {
+        hmap.trace_outlines(components);
+        for c in &mut components.components {
+            for outline in &mut c.outline {
+                let simplified = outline.simplify(self.tolerance);
+                if simplified.points.len() >= 3 {
+                    *outline = simplified;
+                }
+            }
+        }
+    }
+
+    /// Whether some constraints are set, the map having to be labelled to check them.
+    pub fn constrained(&self) -> bool {
+        self.min_islands.is_some() || self.max_islands.is_some() || self.min_island_area.is_some() ||
+        self.min_land.is_some() || self.max_land.is_some()
+    }
+
+    /// Reason why components of a map of `samples` samples fail the constraints, if they do.
+    pub fn check(&self, components: &[Component], samples: usize) -> Result<(), String> {
+        let islands: Vec<_> = components.iter().filter(|c| c.is_island()).collect();
+        let largest = islands.iter().map(|c| c.area).max().unwrap_or(0) as f64 / samples as f64;
+        let land = components.iter().filter(|c| c.land).map(|c| c.area).sum::<usize>() as f64 / samples as f64;
+
+        if self.min_islands.map_or(false, |min| islands.len() < min) ||
+           self.max_islands.map_or(false, |max| islands.len() > max) {
+            return Err(format!("{} islands", islands.len()));
+        }
+        if self.min_island_area.map_or(false, |min| largest < min) {
+            return Err(format!("largest island covering {:.4} of the map", largest));
+        }
+        if self.min_land.map_or(false, |min| land < min) || self.max_land.map_or(false, |max| land > max) {
+            return Err(format!("land covering {:.4} of the map", land));
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes the components as polygons, in the coordinates of `georef` for a map `rows` samples high,
+/// with their kind, area, perimeter and centroid in map units.
+pub fn write_components<W: Write>(mut w: W,
+                                  components: &[Component],
+                                  georef: &GeoReference,
+                                  rows: u32)
+                                  -> io::Result<()> {
+    let features: Vec<_> = components.iter()
+        .map(|c| {
+            // Rings go counterclockwise around the polygon once the rows are flipped, and are closed
+            let rings: Vec<_> = c.outline
+                .iter()
+                .map(|o| {
+                    let mut ring: Vec<_> = o.points
+                        .iter()
+                        .rev()
+                        .map(|&(x, y)| {
+                            let (x, y) = georef.position(x, y, rows);
+                            json!([x, y])
+                        })
+                        .collect();
+                    if let Some(first) = ring.first().cloned() {
+                        ring.push(first);
+                    }
+                    ring
+                })
+                .collect();
+            let (x, y) = georef.position(c.centroid.0, c.centroid.1, rows);
+            let kind = match (c.land, c.border) {
+                (true, false) => "island",
+                (true, true) => "land",
+                (false, false) => "lake",
+                (false, true) => "sea",
+            };
+
+            json!({
+                "type": "Feature",
+                "geometry": {"type": "Polygon", "coordinates": rings},
+                "properties": {
+                    "kind": kind,
+                    "area": c.area as f64 * georef.cell_size * georef.cell_size,
+                    "perimeter": c.perimeter * georef.cell_size,
+                    "centroid": [x, y],
+                },
+            })
+        })
+        .collect();
+
+    let collection = json!({"type": "FeatureCollection", "features": features});
+    serde_json::to_writer_pretty(&mut w, &collection).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+    w.flush()
+}
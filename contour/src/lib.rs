@@ -0,0 +1,19 @@
+extern crate heightmap;
+extern crate image;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+
+mod contour;
+mod draw;
+mod export;
+mod landmass;
+mod vector;
+
+pub use contour::{ContourFormat, ContourOptions};
+pub use draw::draw_contours;
+pub use export::{write_geojson, write_svg};
+pub use landmass::{LandmassOptions, write_components};
+pub use vector::VectorOptions;
@@ -0,0 +1,229 @@
+//! Maps drawn as vector shapes, scaling cleanly when printed.
+use contour::ContourOptions;
+use export::{svg_color, svg_labels, svg_lines};
+use heightmap::{Contour, Heightmap, Sample};
+use image::{Color, ColorRamp, GeoReference};
+use std::cmp::Ordering;
+use std::io;
+use std::io::Write;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct VectorOptions {
+    /// Height between two bands, each filled with the color of the ramp at its lowest height.
+    #[serde(default = "default_interval")]
+    pub interval: f64,
+    /// Largest distance in samples between a simplified shape and the traced one.
+    #[serde(default = "default_tolerance")]
+    pub tolerance: f64,
+    #[serde(default = "default_coast_color")]
+    pub coast_color: Color,
+    #[serde(default = "default_coast_width")]
+    pub coast_width: f64,
+    /// Share of the map draining through a sample from which it is a river, `0` for none.
+    #[serde(default = "default_river")]
+    pub river: f64,
+    #[serde(default = "default_river_color")]
+    pub river_color: Color,
+    #[serde(default = "default_river_width")]
+    pub river_width: f64,
+    /// Number of the highest summits marked with their elevation.
+    #[serde(default = "default_peaks")]
+    pub peaks: usize,
+}
+
+fn default_interval() -> f64 {
+    0.025
+}
+
+fn default_tolerance() -> f64 {
+    0.5
+}
+
+fn default_coast_color() -> Color {
+    Color::new([0x22u8, 0x33u8, 0x44u8])
+}
+
+fn default_coast_width() -> f64 {
+    1.5
+}
+
+fn default_river() -> f64 {
+    0.002
+}
+
+fn default_river_color() -> Color {
+    Color::new([0x33u8, 0x66u8, 0xAAu8])
+}
+
+fn default_river_width() -> f64 {
+    1.0
+}
+
+fn default_peaks() -> usize {
+    5
+}
+
+impl Default for VectorOptions {
+    fn default() -> VectorOptions {
+        VectorOptions {
+            interval: default_interval(),
+            tolerance: default_tolerance(),
+            coast_color: default_coast_color(),
+            coast_width: default_coast_width(),
+            river: default_river(),
+            river_color: default_river_color(),
+            river_width: default_river_width(),
+            peaks: default_peaks(),
+        }
+    }
+}
+
+/// Path through the points of each line, closed ones coming back to their first point.
+fn path_data(lines: &[Contour]) -> String {
+    let mut commands = Vec::new();
+    for line in lines.iter().filter(|l| l.points.len() > 1) {
+        for (i, &(x, y)) in line.points.iter().enumerate() {
+            commands.push(format!("{}{:.2} {:.2}", if i == 0 { "M" } else { "L" }, x, y));
+        }
+        if line.closed {
+            commands.push("Z".to_string());
+        }
+    }
+
+    commands.join(" ")
+}
+
+impl VectorOptions {
+    /// Writes the map as SVG: bands of height filled with the colors of `ramp`, the coastline at
+    /// `sea_level`, the rivers, the contours if any, and the summits.
+    pub fn write_svg<W, T>(&self,
+                           mut w: W,
+                           hmap: &Heightmap<T>,
+                           sea_level: f64,
+                           ramp: &ColorRamp,
+                           contours: Option<&ContourOptions>,
+                           georef: &GeoReference)
+                           -> io::Result<()>
+        where W: Write,
+              T: Sample
+    {
+        let (width, height) = (hmap.width(), hmap.height());
+        let (min, max) = hmap.heights()
+            .fold((::std::f64::INFINITY, ::std::f64::NEG_INFINITY),
+                  |(min, max), h| (min.min(h.to_f64()), max.max(h.to_f64())));
+        // The view goes from the first sample to the last one, where the outlines of the bands run
+        let (right, bottom) = (width.saturating_sub(1), height.saturating_sub(1));
+        writeln!(w,
+                 r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+                 width,
+                 height,
+                 right,
+                 bottom)?;
+
+        // Each band covers the ones below it, the areas above a height being the inside of an odd
+        // number of its outlines
+        writeln!(w,
+                 r#"<rect width="{}" height="{}" fill="{}"/>"#,
+                 right,
+                 bottom,
+                 svg_color(ramp.get(min)))?;
+        if self.interval > 0.0 {
+            let first = ((min - sea_level) / self.interval).floor() as i64 + 1;
+            let last = ((max - sea_level) / self.interval).floor() as i64;
+            for i in first..last + 1 {
+                let level = sea_level + i as f64 * self.interval;
+                let outlines: Vec<_> = hmap.outlines(level).iter().map(|c| c.simplify(self.tolerance)).collect();
+                writeln!(w,
+                         r#"<path fill="{}" fill-rule="evenodd" d="{}"/>"#,
+                         svg_color(ramp.get(level)),
+                         path_data(&outlines))?;
+            }
+        }
+
+        let coast: Vec<_> = hmap.outlines(sea_level).iter().map(|c| c.simplify(self.tolerance)).collect();
+        writeln!(w,
+                 r#"<path fill="none" stroke="{}" stroke-width="{}" stroke-linejoin="round" d="{}"/>"#,
+                 svg_color(self.coast_color),
+                 self.coast_width,
+                 path_data(&coast))?;
+
+        if self.river > 0.0 {
+            let rivers: Vec<_> = hmap.rivers(self.river * f64::from(width) * f64::from(height), sea_level)
+                .into_iter()
+                .map(|course| {
+                    Contour {
+                            level: sea_level,
+                            points: course.into_iter().map(|(x, y)| (f64::from(x), f64::from(y))).collect(),
+                            closed: false,
+                        }
+                        .simplify(self.tolerance)
+                })
+                .collect();
+            writeln!(w,
+                     r#"<path fill="none" stroke="{}" stroke-width="{}" stroke-linejoin="round" d="{}"/>"#,
+                     svg_color(self.river_color),
+                     self.river_width,
+                     path_data(&rivers))?;
+        }
+
+        if let Some(options) = contours {
+            let lines: Vec<_> = options.trace(hmap).iter().map(|c| c.simplify(self.tolerance)).collect();
+            svg_lines(&mut w, &lines, options)?;
+            svg_labels(&mut w, &lines, options, georef)?;
+        }
+
+        self.write_peaks(&mut w, hmap, sea_level, georef)?;
+        writeln!(w, "</svg>")?;
+
+        w.flush()
+    }
+
+    /// Triangles on the highest samples above the sea, an eighth of the map apart, with their
+    /// elevation. Only the highest sample of each square of that size is a candidate.
+    fn write_peaks<W, T>(&self, w: &mut W, hmap: &Heightmap<T>, sea_level: f64, georef: &GeoReference) -> io::Result<()>
+        where W: Write,
+              T: Sample
+    {
+        let (width, height) = (hmap.width(), hmap.height());
+        let spacing = f64::from(width.max(height)) / 8.0;
+        let size = (spacing as u32).max(1);
+        let mut candidates: Vec<(u32, u32, f64)> = Vec::new();
+        for top in (0..height).step_by(size as usize) {
+            for left in (0..width).step_by(size as usize) {
+                let highest = (top..(top + size).min(height))
+                    .flat_map(|y| (left..(left + size).min(width)).map(move |x| (x, y)))
+                    .map(|(x, y)| (x, y, hmap.get(x, y)))
+                    .filter(|&(_, _, h)| h > sea_level)
+                    .max_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(Ordering::Equal));
+                if let Some(c) = highest {
+                    candidates.push(c);
+                }
+            }
+        }
+        candidates.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(Ordering::Equal));
+
+        let mut peaks: Vec<(u32, u32)> = Vec::new();
+        for (x, y, _) in candidates {
+            if peaks.len() >= self.peaks {
+                break;
+            }
+            if peaks.iter().all(|&(u, v)| (f64::from(u) - f64::from(x)).hypot(f64::from(v) - f64::from(y)) >= spacing) {
+                peaks.push((x, y));
+            }
+        }
+
+        writeln!(w, r#"<g font-family="serif" font-size="10" text-anchor="middle" fill="black""#)?;
+        writeln!(w, r#"   stroke="white" stroke-width="3" paint-order="stroke">"#)?;
+        for (x, y) in peaks {
+            writeln!(w,
+                     r#"<path d="M{} {} l-4 7 h8 z"/><text x="{}" y="{}">{}</text>"#,
+                     x,
+                     f64::from(y) - 3.5,
+                     x,
+                     f64::from(y) + 14.0,
+                     georef.elevation(hmap.get(x, y)).round())?;
+        }
+
+        writeln!(w, "</g>")
+    }
+}
@@ -0,0 +1,248 @@
+//! Terrain analysis: rasters derived from the neighbourhood of each sample.
+//!
+//! `scale` is the height of a sample at `1.0`, in distances between two samples. Samples past the
+//! borders repeat the border ones.
+use heightmap::{Heightmap, heightmap_from_vec};
+use sample::Sample;
+
+/// Offsets of the 8 neighbours of a sample.
+const NEIGHBOURS: [(i64, i64); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+
+/// Heights around a sample, `z[1][1]` being the sample and `z[0]` the row above.
+type Window = [[f64; 3]; 3];
+
+impl<T> Heightmap<T>
+    where T: Sample
+{
+    fn window(&self, x: u32, y: u32, scale: f64) -> Window {
+        let (x, y) = (i64::from(x), i64::from(y));
+        let (width, height) = (i64::from(self.width()), i64::from(self.height()));
+
+        let mut z = [[0.0; 3]; 3];
+        for (j, row) in z.iter_mut().enumerate() {
+            for (i, h) in row.iter_mut().enumerate() {
+                let (u, v) = (x + i as i64 - 1, y + j as i64 - 1);
+                *h = self.get(u.max(0).min(width - 1) as u32, v.max(0).min(height - 1) as u32) * scale;
+            }
+        }
+
+        z
+    }
+
+    fn derive<F>(&self, scale: f64, f: F) -> Heightmap
+        where F: Fn(&Window) -> f64
+    {
+        let data = (0..self.height())
+            .flat_map(|y| (0..self.width()).map(move |x| (x, y)))
+            .map(|(x, y)| f(&self.window(x, y, scale)))
+            .collect();
+
+        heightmap_from_vec(self.width(), self.height(), data)
+    }
+
+    /// Slope towards the right and the bottom of the map at a sample, weighing its 8 neighbours
+    /// (Horn, or Sobel). Shared by the analyses, the shading and the normal maps.
+    pub fn gradient(&self, x: u32, y: u32, scale: f64) -> (f64, f64) {
+        gradient(&self.window(x, y, scale))
+    }
+
+    /// Same as `gradient` from the two neighbours along each axis only (central differences), the
+    /// difference with the single neighbour inside the map being taken on its borders.
+    pub fn central_gradient(&self, x: u32, y: u32, scale: f64) -> (f64, f64) {
+        let (x0, x1) = (x.saturating_sub(1), (x + 1).min(self.width() - 1));
+        let (y0, y1) = (y.saturating_sub(1), (y + 1).min(self.height() - 1));
+        let difference = |a: f64, b: f64, distance: u32| if distance > 0 {
+            (b - a) * scale / f64::from(distance)
+        } else {
+            0.0
+        };
+
+        (difference(self.get(x0, y), self.get(x1, y), x1 - x0),
+         difference(self.get(x, y0), self.get(x, y1), y1 - y0))
+    }
+
+    /// Steepness in degrees, from `0` on flat ground to `90`.
+    pub fn slope(&self, scale: f64) -> Heightmap {
+        self.derive(scale, |z| {
+            let (dx, dy) = gradient(z);
+            (dx * dx + dy * dy).sqrt().atan().to_degrees()
+        })
+    }
+
+    /// Direction the slope faces in degrees, clockwise from the top of the map, `-1` on flat ground.
+    pub fn aspect(&self, scale: f64) -> Heightmap {
+        self.derive(scale, |z| {
+            let (dx, dy) = gradient(z);
+            if dx.hypot(dy) < ::std::f64::EPSILON {
+                -1.0
+            } else {
+                // Downhill is (-dx, -dy), the top of the map being towards -y
+                let angle = (-dx).atan2(dy).to_degrees();
+                if angle < 0.0 { angle + 360.0 } else { angle }
+            }
+        })
+    }
+
+    /// Curvature along the slope: positive where it gets steeper downhill (convex), negative where it
+    /// flattens (concave).
+    pub fn profile_curvature(&self, scale: f64) -> Heightmap {
+        self.derive(scale, |z| {
+            let (d, e, f, g, h) = coefficients(z);
+            if g == 0.0 && h == 0.0 {
+                0.0
+            } else {
+                -2.0 * (d * g * g + e * h * h + f * g * h) / (g * g + h * h)
+            }
+        })
+    }
+
+    /// Curvature across the slope: positive on ridges and spurs, negative in valleys.
+    pub fn plan_curvature(&self, scale: f64) -> Heightmap {
+        self.derive(scale, |z| {
+            let (d, e, f, g, h) = coefficients(z);
+            if g == 0.0 && h == 0.0 {
+                0.0
+            } else {
+                -2.0 * (d * h * h + e * g * g - f * g * h) / (g * g + h * h)
+            }
+        })
+    }
+
+    /// Terrain ruggedness index: root of the sum of the squared differences with the neighbours.
+    pub fn ruggedness(&self, scale: f64) -> Heightmap {
+        self.derive(scale, |z| {
+            z.iter()
+                .flat_map(|row| row.iter())
+                .map(|&h| (h - z[1][1]) * (h - z[1][1]))
+                .sum::<f64>()
+                .sqrt()
+        })
+    }
+
+    /// Samples from the highest to the lowest, with the neighbour each one drains to: water flows to
+    /// the neighbour of steepest descent (D8) and stops in pits.
+    fn drainage(&self) -> (Vec<usize>, Vec<Option<usize>>) {
+        let (width, height) = (self.width() as usize, self.height() as usize);
+        let mut order: Vec<usize> = (0..width * height).collect();
+        let h = |i: usize| self.get((i % width) as u32, (i / width) as u32);
+        order.sort_by(|&a, &b| h(b).partial_cmp(&h(a)).unwrap_or(::std::cmp::Ordering::Equal));
+
+        let receivers = (0..width * height)
+            .map(|i| {
+                let (x, y) = ((i % width) as i64, (i / width) as i64);
+                let mut lowest = None;
+                let mut steepest = 0.0;
+                for (dx, dy) in NEIGHBOURS.iter().cloned() {
+                    let (u, v) = (x + dx, y + dy);
+                    if u < 0 || v < 0 || u >= width as i64 || v >= height as i64 {
+                        continue;
+                    }
+
+                    let n = v as usize * width + u as usize;
+                    let drop = (h(i) - h(n)) / (dx as f64).hypot(dy as f64);
+                    if drop > steepest {
+                        steepest = drop;
+                        lowest = Some(n);
+                    }
+                }
+                lowest
+            })
+            .collect();
+
+        (order, receivers)
+    }
+
+    /// Number of samples draining through each sample, itself included: water flows to the
+    /// neighbour of steepest descent (D8) and stops in pits.
+    pub fn flow_accumulation(&self) -> Heightmap {
+        let (order, receivers) = self.drainage();
+        heightmap_from_vec(self.width(), self.height(), accumulate(&order, &receivers))
+    }
+
+    /// Courses of the rivers, as the samples through which at least `threshold` samples drain, from
+    /// their source down to the sea below `sea_level`, a pit, or the river they join.
+    pub fn rivers(&self, threshold: f64, sea_level: f64) -> Vec<Vec<(u32, u32)>> {
+        let width = self.width() as usize;
+        let (order, receivers) = self.drainage();
+        let flow = accumulate(&order, &receivers);
+
+        // Sources have no river upstream, the highest ones giving the longest rivers
+        let position = |i: usize| ((i % width) as u32, (i / width) as u32);
+        let is_river = |i: usize| flow[i] >= threshold && self.get(position(i).0, position(i).1) >= sea_level;
+        let mut fed = vec![false; order.len()];
+        for &i in &order {
+            if let (true, Some(n)) = (is_river(i), receivers[i]) {
+                fed[n] = true;
+            }
+        }
+
+        let mut traced = vec![false; order.len()];
+        let mut rivers = Vec::new();
+        for &source in order.iter().filter(|&&i| is_river(i) && !fed[i]) {
+            let mut course = vec![position(source)];
+            traced[source] = true;
+            let mut current = source;
+            while let Some(n) = receivers[current] {
+                course.push(position(n));
+                if traced[n] || !is_river(n) {
+                    break;
+                }
+                traced[n] = true;
+                current = n;
+            }
+            rivers.push(course);
+        }
+
+        rivers
+    }
+
+    /// `1.0` where the value is between `low` and `high`, `0.0` elsewhere, with a linear transition
+    /// of width `falloff` on each side.
+    pub fn mask(&self, low: f64, high: f64, falloff: f64) -> Heightmap {
+        let data = self.heights()
+            .map(|h| {
+                let h = h.to_f64();
+                let distance = (low - h).max(h - high);
+                if distance <= 0.0 {
+                    1.0
+                } else if distance < falloff {
+                    1.0 - distance / falloff
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+
+        heightmap_from_vec(self.width(), self.height(), data)
+    }
+}
+
+/// Slope towards the right and the bottom of the map (Horn).
+fn gradient(z: &Window) -> (f64, f64) {
+    ((z[0][2] + 2.0 * z[1][2] + z[2][2] - z[0][0] - 2.0 * z[1][0] - z[2][0]) / 8.0,
+     (z[2][0] + 2.0 * z[2][1] + z[2][2] - z[0][0] - 2.0 * z[0][1] - z[0][2]) / 8.0)
+}
+
+/// Coefficients of the quadratic surface through the window (Zevenbergen and Thorne), with `y`
+/// towards the top of the map.
+fn coefficients(z: &Window) -> (f64, f64, f64, f64, f64) {
+    let d = (z[1][0] + z[1][2]) / 2.0 - z[1][1];
+    let e = (z[0][1] + z[2][1]) / 2.0 - z[1][1];
+    let f = (-z[0][0] + z[0][2] + z[2][0] - z[2][2]) / 4.0;
+    let g = (z[1][2] - z[1][0]) / 2.0;
+    let h = (z[0][1] - z[2][1]) / 2.0;
+
+    (d, e, f, g, h)
+}
+
+/// Samples draining through each sample, visited from the highest.
+fn accumulate(order: &[usize], receivers: &[Option<usize>]) -> Vec<f64> {
+    let mut flow = vec![1.0; order.len()];
+    for &i in order {
+        if let Some(n) = receivers[i] {
+            flow[n] += flow[i];
+        }
+    }
+
+    flow
+}
@@ -0,0 +1,192 @@
+//! Land and water masses: samples connected by their sides above or below the sea level.
+use contour::Contour;
+use heightmap::{Heightmap, heightmap_from_vec};
+use sample::Sample;
+use std::cmp::Ordering;
+
+/// Island, continent, sea or lake.
+#[derive(Clone, Debug)]
+pub struct Component {
+    pub land: bool,
+    /// Number of samples.
+    pub area: usize,
+    /// Length of the outlines in distances between samples, the borders of the map excluded. `0`
+    /// until the outlines are traced.
+    pub perimeter: f64,
+    /// Mean position of the samples.
+    pub centroid: (f64, f64),
+    /// Whether the component reaches the borders of the map, hiding its full extent.
+    pub border: bool,
+    /// Outline around the component first, turning counterclockwise on the map, then the ones
+    /// around its holes. Empty until traced by `trace_outlines`.
+    pub outline: Vec<Contour>,
+}
+
+impl Component {
+    pub fn is_island(&self) -> bool {
+        self.land && !self.border
+    }
+}
+
+pub struct Components {
+    /// Height separating the land from the water.
+    pub sea_level: f64,
+    /// Index of the component of each sample, row after row.
+    pub labels: Vec<usize>,
+    pub components: Vec<Component>,
+}
+
+/// Area enclosed by a closed line, positive when it turns clockwise on the map.
+fn signed_area(points: &[(f64, f64)]) -> f64 {
+    let n = points.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % n]);
+            a.0 * b.1 - b.0 * a.1
+        })
+        .sum::<f64>() / 2.0
+}
+
+impl<T> Heightmap<T>
+    where T: Sample
+{
+    /// Labels the land at or above `sea_level` and the water below it, with the area, centroid and
+    /// position of each component but without their outlines.
+    pub fn components(&self, sea_level: f64) -> Components {
+        let (width, height) = (self.width() as usize, self.height() as usize);
+        let land = |i: usize| self.get((i % width) as u32, (i / width) as u32) >= sea_level;
+        let mut labels = vec![usize::max_value(); width * height];
+        let mut members = Vec::new();
+
+        for seed in 0..width * height {
+            if labels[seed] != usize::max_value() {
+                continue;
+            }
+
+            let (index, is_land) = (members.len(), land(seed));
+            let mut samples = vec![seed];
+            let mut stack = vec![seed];
+            labels[seed] = index;
+            while let Some(i) = stack.pop() {
+                let (x, y) = ((i % width) as i64, (i / width) as i64);
+                for &(dx, dy) in &[(0, -1), (-1, 0), (1, 0), (0, 1)] {
+                    let (u, v) = (x + dx, y + dy);
+                    if u < 0 || v < 0 || u >= width as i64 || v >= height as i64 {
+                        continue;
+                    }
+
+                    let n = v as usize * width + u as usize;
+                    if labels[n] == usize::max_value() && land(n) == is_land {
+                        labels[n] = index;
+                        samples.push(n);
+                        stack.push(n);
+                    }
+                }
+            }
+            members.push((is_land, samples));
+        }
+
+        let components = members.iter()
+            .map(|&(is_land, ref samples)| {
+                let positions: Vec<(usize, usize)> = samples.iter().map(|&i| (i % width, i / width)).collect();
+                let (x0, y0) = positions.iter().fold((width, height), |(x, y), &(u, v)| (x.min(u), y.min(v)));
+                let (x1, y1) = positions.iter().fold((0, 0), |(x, y), &(u, v)| (x.max(u), y.max(v)));
+                let (sx, sy) = positions.iter().fold((0.0, 0.0), |(x, y), &(u, v)| (x + u as f64, y + v as f64));
+
+                Component {
+                    land: is_land,
+                    area: samples.len(),
+                    perimeter: 0.0,
+                    centroid: (sx / samples.len() as f64, sy / samples.len() as f64),
+                    border: x0 == 0 || y0 == 0 || x1 == width - 1 || y1 == height - 1,
+                    outline: Vec::new(),
+                }
+            })
+            .collect();
+
+        Components {
+            sea_level,
+            labels,
+            components,
+        }
+    }
+
+    /// Traces the outlines of labelled components and measures their perimeters.
+    pub fn trace_outlines(&self, components: &mut Components) {
+        let width = self.width() as usize;
+        let mut bounds = vec![(usize::max_value(), usize::max_value(), 0, 0); components.components.len()];
+        for (i, &label) in components.labels.iter().enumerate() {
+            let (x, y) = (i % width, i / width);
+            let b = &mut bounds[label];
+            *b = (b.0.min(x), b.1.min(y), b.2.max(x), b.3.max(y));
+        }
+
+        for (index, component) in components.components.iter_mut().enumerate() {
+            let (outline, perimeter) =
+                self.outline(&components.labels, index, component.land, bounds[index], components.sea_level);
+            component.outline = outline;
+            component.perimeter = perimeter;
+        }
+    }
+
+    /// Outlines of a component whose samples are within `(x0, y0, x1, y1)`, and their length.
+    fn outline(&self,
+               labels: &[usize],
+               index: usize,
+               land: bool,
+               (x0, y0, x1, y1): (usize, usize, usize, usize),
+               sea_level: f64)
+               -> (Vec<Contour>, f64) {
+        let (width, height) = (self.width() as usize, self.height() as usize);
+
+        // Heights around the component, water ones mirrored so that it is above the sea level
+        // either way, other components of the same kind being left out so that the component has
+        // a single outline around it
+        let (left, top) = (x0.saturating_sub(1), y0.saturating_sub(1));
+        let (right, bottom) = ((x1 + 1).min(width - 1), (y1 + 1).min(height - 1));
+        let crop = (top..bottom + 1)
+            .flat_map(|y| (left..right + 1).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let h = self.get(x as u32, y as u32);
+                let h = if land { h } else { 2.0 * sea_level - h };
+                if labels[y * width + x] == index || h < sea_level {
+                    h
+                } else {
+                    ::std::f64::NEG_INFINITY
+                }
+            })
+            .collect();
+        let crop = heightmap_from_vec((right - left + 1) as u32, (bottom - top + 1) as u32, crop);
+
+        let mut outline: Vec<Contour> = crop.outlines(sea_level)
+            .into_iter()
+            .map(|c| {
+                Contour {
+                    level: sea_level,
+                    points: c.points.iter().map(|&(x, y)| (x + left as f64, y + top as f64)).collect(),
+                    closed: true,
+                }
+            })
+            .collect();
+        outline.sort_by(|a, b| {
+            signed_area(&b.points).abs().partial_cmp(&signed_area(&a.points).abs()).unwrap_or(Ordering::Equal)
+        });
+
+        // Runs along the borders of the map are not coasts
+        let (last_x, last_y) = ((width - 1) as f64, (height - 1) as f64);
+        let on_border = |a: (f64, f64), b: (f64, f64)| {
+            (a.0 == 0.0 && b.0 == 0.0) || (a.1 == 0.0 && b.1 == 0.0) || (a.0 == last_x && b.0 == last_x) ||
+            (a.1 == last_y && b.1 == last_y)
+        };
+        let perimeter = outline.iter()
+            .flat_map(|c| {
+                let n = c.points.len();
+                (0..n).map(move |i| (c.points[i], c.points[(i + 1) % n]))
+            })
+            .filter(|&(a, b)| !on_border(a, b))
+            .map(|(a, b)| (b.0 - a.0).hypot(b.1 - a.1))
+            .sum();
+
+        (outline, perimeter)
+    }
+}
@@ -0,0 +1,355 @@
+//! Lines along which the height is constant, traced by marching squares.
+//!
+//! Positions are in samples from the top left corner of the map, fractional positions being between
+//! samples.
+use heightmap::Heightmap;
+use sample::Sample;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+
+/// Line at a constant height.
+#[derive(Clone, Debug)]
+pub struct Contour {
+    pub level: f64,
+    pub points: Vec<(f64, f64)>,
+    /// Whether the last point joins the first one, open lines ending at the borders of the map.
+    pub closed: bool,
+}
+
+impl Contour {
+    /// Fewer points, none of the removed ones being farther than `tolerance` from the simplified
+    /// line (Douglas-Peucker).
+    pub fn simplify(&self, tolerance: f64) -> Contour {
+        let points = &self.points;
+        let mut keep = vec![false; points.len()];
+        if points.len() > 2 {
+            // Closed lines are split at the point farthest from the first one
+            let last = if self.closed {
+                let (x0, y0) = points[0];
+                (1..points.len())
+                    .max_by(|&a, &b| {
+                        let (d1, d2) = ((points[a].0 - x0).hypot(points[a].1 - y0),
+                                        (points[b].0 - x0).hypot(points[b].1 - y0));
+                        d1.partial_cmp(&d2).unwrap_or(Ordering::Equal)
+                    })
+                    .unwrap()
+            } else {
+                points.len() - 1
+            };
+            keep[0] = true;
+            keep[last] = true;
+            douglas_peucker(points, 0, last, tolerance, &mut keep);
+            if self.closed {
+                douglas_peucker_closing(points, last, tolerance, &mut keep);
+            }
+        } else {
+            keep = vec![true; points.len()];
+        }
+
+        Contour {
+            level: self.level,
+            points: points.iter().zip(keep).filter(|&(_, k)| k).map(|(&p, _)| p).collect(),
+            closed: self.closed,
+        }
+    }
+}
+
+/// Distance from `p` to the segment between `a` and `b`.
+fn distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length = dx * dx + dy * dy;
+    let t = if length > 0.0 { (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length).max(0.0).min(1.0) } else { 0.0 };
+
+    (p.0 - a.0 - t * dx).hypot(p.1 - a.1 - t * dy)
+}
+
+/// Keeps the points between `first` and `last` needed to stay within `tolerance`.
+fn douglas_peucker(points: &[(f64, f64)], first: usize, last: usize, tolerance: f64, keep: &mut [bool]) {
+    let farthest = (first + 1..last)
+        .map(|i| (i, distance(points[i], points[first], points[last])))
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+
+    if let Some((i, d)) = farthest {
+        if d > tolerance {
+            keep[i] = true;
+            douglas_peucker(points, first, i, tolerance, keep);
+            douglas_peucker(points, i, last, tolerance, keep);
+        }
+    }
+}
+
+/// Same as `douglas_peucker` from `first` back to the first point of a closed line.
+fn douglas_peucker_closing(points: &[(f64, f64)], first: usize, tolerance: f64, keep: &mut [bool]) {
+    let mut rotated: Vec<_> = points[first..].to_vec();
+    rotated.push(points[0]);
+    let mut kept = vec![false; rotated.len()];
+    douglas_peucker(&rotated, 0, rotated.len() - 1, tolerance, &mut kept);
+
+    for (i, k) in kept.into_iter().enumerate().filter(|&(_, k)| k) {
+        keep[(first + i) % points.len()] = k;
+    }
+}
+
+/// Side between two neighbouring samples: the top or left one, and whether the other one is below
+/// it rather than on its right. Samples may be outside of the map.
+type Edge = (i64, i64, bool);
+
+impl<T> Heightmap<T>
+    where T: Sample
+{
+    /// Contours at each multiple of `interval` from `base`, between the lowest and the highest samples.
+    pub fn contours(&self, base: f64, interval: f64) -> Vec<Contour> {
+        if interval <= 0.0 {
+            return Vec::new();
+        }
+
+        let (min, max) = self.heights()
+            .fold((::std::f64::INFINITY, ::std::f64::NEG_INFINITY),
+                  |(min, max), h| (min.min(h.to_f64()), max.max(h.to_f64())));
+        let (first, last) = (((min - base) / interval).ceil() as i64, ((max - base) / interval).floor() as i64);
+
+        (first..last + 1)
+            .flat_map(|i| self.contour(base + i as f64 * interval))
+            .collect()
+    }
+
+    /// Lines at `level`, between the samples below it and the ones at or above it.
+    pub fn contour(&self, level: f64) -> Vec<Contour> {
+        self.trace(level, false)
+    }
+
+    /// Closed lines around the areas at or above `level`, following the borders of the map where the
+    /// areas reach them. Lines around holes turn the other way: a point is inside the areas when
+    /// it is inside an odd number of lines.
+    pub fn outlines(&self, level: f64) -> Vec<Contour> {
+        self.trace(level, true)
+    }
+
+    /// Height of a sample, samples outside of the map being below everything.
+    fn padded(&self, x: i64, y: i64) -> f64 {
+        if x < 0 || y < 0 || x >= i64::from(self.width()) || y >= i64::from(self.height()) {
+            ::std::f64::NEG_INFINITY
+        } else {
+            self.get(x as u32, y as u32)
+        }
+    }
+
+    /// Lines at `level`, going around the map when `bordered` so that they are all closed.
+    fn trace(&self, level: f64, bordered: bool) -> Vec<Contour> {
+        let (width, height) = (i64::from(self.width()), i64::from(self.height()));
+        let border = if bordered { 1 } else { 0 };
+        let above = |x: i64, y: i64| self.padded(x, y) >= level;
+
+        // Each square between four samples joins the sides the line crosses, keeping the areas above
+        // on the same side of the lines
+        let mut links: BTreeMap<Edge, Edge> = BTreeMap::new();
+        for y in -border..height - 1 + border {
+            for x in -border..width - 1 + border {
+                let case = above(x, y) as u8 | (above(x + 1, y) as u8) << 1 | (above(x + 1, y + 1) as u8) << 2 |
+                           (above(x, y + 1) as u8) << 3;
+                let (top, bottom, left, right) = ((x, y, false), (x, y + 1, false), (x, y, true), (x + 1, y, true));
+
+                // On saddles, the height of the center tells which corners are connected
+                let center = [(x, y), (x + 1, y), (x + 1, y + 1), (x, y + 1)]
+                    .iter()
+                    .map(|&(u, v)| self.padded(u, v))
+                    .sum::<f64>() / 4.0 >= level;
+                let segments = match case {
+                    1 => vec![(left, top)],
+                    2 => vec![(top, right)],
+                    3 => vec![(left, right)],
+                    4 => vec![(right, bottom)],
+                    5 if center => vec![(left, bottom), (right, top)],
+                    5 => vec![(left, top), (right, bottom)],
+                    6 => vec![(top, bottom)],
+                    7 => vec![(left, bottom)],
+                    8 => vec![(bottom, left)],
+                    9 => vec![(bottom, top)],
+                    10 if center => vec![(top, left), (bottom, right)],
+                    10 => vec![(top, right), (bottom, left)],
+                    11 => vec![(bottom, right)],
+                    12 => vec![(right, left)],
+                    13 => vec![(right, top)],
+                    14 => vec![(top, left)],
+                    _ => Vec::new(),
+                };
+                for (a, b) in segments {
+                    links.insert(a, b);
+                }
+            }
+        }
+
+        // Open lines start where nothing leads, closed ones anywhere
+        let ends: HashSet<Edge> = links.values().cloned().collect();
+        let mut starts: Vec<Edge> = links.keys().cloned().filter(|e| !ends.contains(e)).collect();
+        starts.extend(links.keys().cloned());
+
+        let mut visited = HashSet::new();
+        let mut contours = Vec::new();
+        for start in starts {
+            if visited.contains(&start) {
+                continue;
+            }
+
+            let mut edges = vec![start];
+            visited.insert(start);
+            let mut closed = false;
+            while let Some(&next) = links.get(edges.last().unwrap()) {
+                if next == start {
+                    closed = true;
+                    break;
+                }
+                visited.insert(next);
+                edges.push(next);
+            }
+
+            let mut points: Vec<(f64, f64)> = edges.iter().map(|&e| self.crossing(e, level)).collect();
+            points.dedup();
+            if closed && points.len() > 1 && points.first() == points.last() {
+                points.pop();
+            }
+            contours.push(Contour {
+                level,
+                points,
+                closed,
+            });
+        }
+
+        contours
+    }
+
+    /// Position of `level` on a side, interpolated linearly, or on the sample inside the map.
+    fn crossing(&self, (x, y, down): Edge, level: f64) -> (f64, f64) {
+        let a = self.padded(x, y);
+        let b = if down { self.padded(x, y + 1) } else { self.padded(x + 1, y) };
+        let t = if a.is_infinite() {
+            1.0
+        } else if b.is_infinite() {
+            0.0
+        } else if b != a {
+            ((level - a) / (b - a)).max(0.0).min(1.0)
+        } else {
+            0.5
+        };
+
+        if down {
+            (x as f64, y as f64 + t)
+        } else {
+            (x as f64 + t, y as f64)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use heightmap::heightmap_from_vec;
+
+    fn close(a: (f64, f64), b: (f64, f64)) -> bool {
+        (a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9
+    }
+
+    /// Whether the lines are the expected ones, in any order and starting anywhere when closed.
+    fn same_lines(lines: &[Contour], expected: &[Vec<(f64, f64)>]) -> bool {
+        lines.len() == expected.len() &&
+        expected.iter().all(|points| {
+            lines.iter().any(|l| {
+                l.points.len() == points.len() &&
+                (0..points.len()).any(|shift| {
+                    (shift == 0 || l.closed) &&
+                    points.iter().enumerate().all(|(i, &p)| close(l.points[(i + shift) % points.len()], p))
+                })
+            })
+        })
+    }
+
+    #[test]
+    fn peaks_are_ringed() {
+        let hmap = heightmap_from_vec(5,
+                                      5,
+                                      vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.5, 0.5, 0.5, 0.0, 0.0, 0.5, 1.0, 0.5, 0.0,
+                                           0.0, 0.5, 0.5, 0.5, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+
+        let inner = hmap.contour(0.75);
+        assert_eq!(inner.len(), 1);
+        assert!(inner[0].closed);
+        assert_eq!(inner[0].points.len(), 4);
+        assert!(inner[0].points.iter().all(|&(x, y)| ((x - 2.0).hypot(y - 2.0) - 0.5).abs() < 1e-9));
+
+        let outer = hmap.contour(0.25);
+        assert_eq!(outer.len(), 1);
+        assert!(outer[0].closed);
+        assert!(outer[0].points.iter().all(|&(x, y)| (x - 2.0).abs() <= 1.5 && (y - 2.0).abs() <= 1.5));
+    }
+
+    #[test]
+    fn saddles_follow_their_center() {
+        let hmap = heightmap_from_vec(2, 2, vec![1.0, 0.0, 0.0, 1.0]);
+
+        // Below the center, the high corners are joined and the low ones cut off
+        assert!(same_lines(&hmap.contour(0.4),
+                           &[vec![(0.0, 0.6), (0.4, 1.0)], vec![(1.0, 0.4), (0.6, 0.0)]]));
+        // Above it, the high corners are cut off
+        assert!(same_lines(&hmap.contour(0.6),
+                           &[vec![(0.0, 0.4), (0.4, 0.0)], vec![(1.0, 0.6), (0.6, 1.0)]]));
+    }
+
+    #[test]
+    fn plateaus_reaching_the_borders() {
+        let hmap = heightmap_from_vec(4, 3, vec![1.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0]);
+
+        // Contours stop at the borders
+        assert!(same_lines(&hmap.contour(0.5), &[vec![(1.5, 2.0), (1.5, 1.0), (1.5, 0.0)]]));
+
+        // Outlines go around the plateau along the borders
+        let outlines = hmap.outlines(0.5);
+        assert_eq!(outlines.len(), 1);
+        assert!(outlines[0].closed);
+        for &corner in &[(0.0, 0.0), (1.5, 0.0), (1.5, 2.0), (0.0, 2.0)] {
+            assert!(outlines[0].points.iter().any(|&p| close(p, corner)), "{:?} missing", corner);
+        }
+        assert!(outlines[0].points.iter().all(|&(x, y)| x >= 0.0 && x <= 1.5 && y >= 0.0 && y <= 2.0));
+    }
+
+    #[test]
+    fn simplified_lines_stay_within_the_tolerance() {
+        let tolerance = 0.3;
+        let wave: Vec<(f64, f64)> = (0..60)
+            .map(|i| {
+                let t = f64::from(i) / 4.0;
+                (t, t.sin() + 0.1 * (3.0 * t).cos())
+            })
+            .collect();
+        let circle: Vec<(f64, f64)> = (0..60)
+            .map(|i| {
+                let a = f64::from(i) * ::std::f64::consts::PI / 30.0;
+                (5.0 * a.cos(), 5.0 * a.sin())
+            })
+            .collect();
+
+        for &(ref points, closed) in &[(wave, false), (circle, true)] {
+            let line = Contour {
+                level: 0.0,
+                points: points.clone(),
+                closed,
+            };
+            let simplified = line.simplify(tolerance);
+            assert!(simplified.points.len() < points.len() && simplified.points.len() > 2);
+            assert_eq!(simplified.points[0], points[0]);
+            if !closed {
+                assert_eq!(simplified.points.last(), points.last());
+            }
+
+            let mut kept = simplified.points.clone();
+            if closed {
+                kept.push(kept[0]);
+            }
+            for &p in points {
+                let nearest = kept.windows(2)
+                    .map(|s| distance(p, s[0], s[1]))
+                    .fold(::std::f64::INFINITY, f64::min);
+                assert!(nearest <= tolerance, "{:?} is {} away", p, nearest);
+            }
+        }
+    }
+}
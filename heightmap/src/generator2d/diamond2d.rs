@@ -4,8 +4,9 @@ use rand::Rng;
 
 use std::cmp::max;
 
-use super::Generator2d;
-use heightmap::{Heightmap, heightmap_from_vec};
+use super::{Generator2d, normalized};
+use heightmap::Heightmap;
+use sample::Sample as HeightSample;
 
 #[derive(Default)]
 pub struct Diamond2d {}
@@ -66,8 +67,9 @@ impl Diamond2d {
 }
 
 impl Generator2d for Diamond2d {
-    fn generate<R>(&self, width: u32, height: u32, rng: &mut R) -> Heightmap
-        where R: Rng
+    fn generate<T, R>(&self, width: u32, height: u32, rng: &mut R) -> Heightmap<T>
+        where T: HeightSample,
+              R: Rng
     {
         let mut sampler = Range::new(0.0, 1.0);
 
@@ -105,6 +107,6 @@ impl Generator2d for Diamond2d {
             d = d_2;
         }
 
-        heightmap_from_vec(size as u32, size as u32, data).submap(0, 0, width, height)
+        normalized(data, size, width, height)
     }
 }
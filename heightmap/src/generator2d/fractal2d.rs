@@ -1,8 +1,9 @@
 use rand::Rng;
 
-use super::Generator2d;
-use heightmap::{Heightmap, heightmap_from_iter};
+use super::{Generator2d, TileGenerator2d};
+use heightmap::{Heightmap, heightmap_from_iter, minmax};
 use noise2d::Noise2d;
+use sample::Sample;
 
 pub struct Fractal2d<N> {
     noise: N,
@@ -39,20 +40,44 @@ impl<N> Fractal2d<N>
 
         value
     }
+
+    /// Raw value of the sample `(x, y)` of a `width` x `height` map.
+    fn at(&self, width: u32, height: u32, (x, y): (u32, u32)) -> f64 {
+        let wt = width as f64;
+        let ht = height as f64;
+        let ratio = wt / ht;
+        self.get((x as f64) / wt * self.scale * ratio, (y as f64) / ht * self.scale)
+    }
 }
 
 impl<N> Generator2d for Fractal2d<N>
     where N: Noise2d
 {
-    fn generate<R: Rng>(&self, width: u32, height: u32, _: &mut R) -> Heightmap {
-        let wt = width as f64;
-        let ht = height as f64;
-        let ratio = wt / ht;
-        let f = |x: u32| ((x % width) as f64) / wt * self.scale * ratio;
-        let g = |y: u32| ((y / width) as f64) / ht * self.scale;
+    /// Stores the raw values and normalizes them in place. Storage types clamping the raw values get
+    /// the noise run twice instead: once to find the range of the values and once to store them, so
+    /// that no raw map is kept alongside the stored one.
+    fn generate<T: Sample, R: Rng>(&self, width: u32, height: u32, _: &mut R) -> Heightmap<T> {
+        let raw = |i: u32| self.at(width, height, (i % width, i / width));
+        if !T::clamps() {
+            let mut hmap = heightmap_from_iter(width, height, (0..width * height).map(|i| T::from_f64(raw(i))));
+            hmap.normalize();
+            return hmap;
+        }
 
+        let (min, max) = minmax((0..width * height).map(&raw)).unwrap_or((0.0, 1.0));
         heightmap_from_iter(width,
                             height,
-                            (0..width * height).map(|i| self.get(f(i), g(i))))
+                            (0..width * height).map(|i| T::from_f64((raw(i) - min) / (max - min))))
+    }
+}
+
+impl<N> TileGenerator2d for Fractal2d<N>
+    where N: Noise2d
+{
+    fn generate_tile(&self, width: u32, height: u32, x: u32, y: u32, tile_width: u32, tile_height: u32) -> Heightmap {
+        let f = |i: u32| (x + i % tile_width, y + i / tile_width);
+        heightmap_from_iter(tile_width,
+                            tile_height,
+                            (0..tile_width * tile_height).map(|i| self.at(width, height, f(i))))
     }
 }
@@ -4,8 +4,9 @@ use rand::Rng;
 
 use std::cmp::max;
 
-use super::Generator2d;
-use heightmap::{Heightmap, heightmap_from_vec};
+use super::{Generator2d, normalized};
+use heightmap::Heightmap;
+use sample::Sample as HeightSample;
 
 #[derive(Default)]
 pub struct Midpoint2d {}
@@ -20,8 +21,9 @@ impl Generator2d for Midpoint2d {
     /// Generates a map using the midpoint displacement algorithm.
     ///
     /// The size doesn't have to be 2^n + 1
-    fn generate<R>(&self, width: u32, height: u32, rng: &mut R) -> Heightmap
-        where R: Rng
+    fn generate<T, R>(&self, width: u32, height: u32, rng: &mut R) -> Heightmap<T>
+        where T: HeightSample,
+              R: Rng
     {
         let mut sampler = Range::new(0.0, 1.0);
 
@@ -65,6 +67,6 @@ impl Generator2d for Midpoint2d {
             d = d_2;
         }
 
-        heightmap_from_vec(size as u32, size as u32, data).submap(0, 0, width, height)
+        normalized(data, size, width, height)
     }
 }
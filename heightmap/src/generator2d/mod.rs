@@ -7,8 +7,33 @@ pub use self::fractal2d::Fractal2d;
 pub use self::midpoint2d::Midpoint2d;
 
 use rand::Rng;
-use heightmap::Heightmap;
+use heightmap::{Heightmap, heightmap_from_iter, minmax};
+use sample::Sample;
 
 pub trait Generator2d {
-    fn generate<R: Rng>(&self, width: u32, height: u32, rng: &mut R) -> Heightmap;
+    /// Generates a map normalized to `[0, 1]`, stored straight into the type of its samples.
+    fn generate<T: Sample, R: Rng>(&self, width: u32, height: u32, rng: &mut R) -> Heightmap<T>;
+}
+
+/// Generator able to compute any part of a map independently of the rest.
+pub trait TileGenerator2d {
+    /// Generates the `tile_width` x `tile_height` area at (`x`, `y`) of a `width` x `height` map.
+    fn generate_tile(&self, width: u32, height: u32, x: u32, y: u32, tile_width: u32, tile_height: u32) -> Heightmap;
+}
+
+/// Normalizes the top left `width` x `height` corner of the `stride` samples wide working buffer of
+/// a generator into a map, the buffer being freed once copied.
+fn normalized<T>(data: Vec<f64>, stride: usize, width: u32, height: u32) -> Heightmap<T>
+    where T: Sample
+{
+    let (min, max) = minmax(corner(&data, stride, width, height)).unwrap_or((0.0, 1.0));
+    heightmap_from_iter(width,
+                        height,
+                        corner(&data, stride, width, height).map(|x| T::from_f64((x - min) / (max - min))))
+}
+
+fn corner<'a>(data: &'a [f64], stride: usize, width: u32, height: u32) -> impl Iterator<Item = f64> + 'a {
+    data.chunks(stride)
+        .take(height as usize)
+        .flat_map(move |row| row[..width as usize].iter().cloned())
 }
@@ -1,10 +1,19 @@
-pub struct Heightmap {
+use sample::Sample;
+
+pub struct Heightmap<T = f64> {
     width: u32,
     height: u32,
-    data: Box<[f64]>,
+    data: Box<[T]>,
 }
 
-impl Heightmap {
+/// Single precision heightmap, half the size of the default one.
+pub type Heightmap32 = Heightmap<f32>;
+/// Normalized 16-bit heightmap, a quarter of the size of the default one.
+pub type Heightmap16 = Heightmap<u16>;
+
+impl<T> Heightmap<T>
+    where T: Sample
+{
     pub fn height(&self) -> u32 {
         self.height
     }
@@ -14,46 +23,33 @@ impl Heightmap {
     }
 
     pub fn get(&self, x: u32, y: u32) -> f64 {
-        self.data[(y * self.width + x) as usize]
+        self.sample(x, y).to_f64()
     }
 
-    fn minmax(&self) -> Option<(f64, f64)> {
-        let mut it = self.data.iter();
-
-        it.next().map(|sel| {
-            let mut min = sel;
-            let mut max = sel;
-
-            for x in it {
-                if x < min {
-                    min = x
-                };
-                if x > max {
-                    max = x
-                };
-            }
+    pub fn set(&mut self, x: u32, y: u32, value: f64) {
+        self.data[(y * self.width + x) as usize] = T::from_f64(value);
+    }
 
-            (*min, *max)
-        })
+    /// Raw stored value, without conversion.
+    pub fn sample(&self, x: u32, y: u32) -> T {
+        self.data[(y * self.width + x) as usize]
     }
 
     pub fn normalize(&mut self) {
-        if let Some((min, max)) = self.minmax() {
+        if let Some((min, max)) = minmax(self.data.iter().map(|x| x.to_f64())) {
             for x in self.data.iter_mut() {
-                *x = (*x - min) / (max - min);
+                *x = T::from_f64((x.to_f64() - min) / (max - min));
             }
         }
     }
 
     pub fn flatten(&mut self) {
-        let h = |x: f64| (x - 0.5) * (x - 0.5) * (if x < 0.5 { -2.0 } else { 2.0 }) + 0.5;
-
         for x in self.data.iter_mut() {
-            *x = h(*x);
+            *x = T::from_f64(flat(x.to_f64()));
         }
     }
 
-    pub fn submap(&self, x: u32, y: u32, width: u32, height: u32) -> Heightmap {
+    pub fn submap(&self, x: u32, y: u32, width: u32, height: u32) -> Heightmap<T> {
         if x + width > self.width {
             panic!("Width out of bounds: {} + {} > {}", x, width, self.width)
         }
@@ -74,11 +70,11 @@ impl Heightmap {
                 data: self.data
                     .chunks(self.width() as usize)
                     .skip(y as usize)
-                    .take((height - y) as usize)
+                    .take(height as usize)
                     .flat_map(|row| {
                                   row.iter()
                                       .skip(x as usize)
-                                      .take((width - x) as usize)
+                                      .take(width as usize)
                                       .cloned()
                               })
                     .collect::<Vec<_>>()
@@ -87,12 +83,52 @@ impl Heightmap {
         }
     }
 
-    pub fn heights(&self) -> impl Iterator<Item = &f64> {
+    pub fn heights(&self) -> impl Iterator<Item = &T> {
         self.data.iter()
     }
+
+    /// Copy the map into another storage type.
+    pub fn convert<U>(&self) -> Heightmap<U>
+        where U: Sample
+    {
+        heightmap_from_iter(self.width,
+                            self.height,
+                            self.data.iter().map(|x| U::from_f64(x.to_f64())))
+    }
+
+    /// Moves the map into another storage type, the original being freed once the whole map is copied.
+    pub fn into_storage<U>(self) -> Heightmap<U>
+        where U: Sample
+    {
+        self.convert()
+    }
+}
+
+pub fn minmax<I>(mut it: I) -> Option<(f64, f64)>
+    where I: Iterator<Item = f64>
+{
+    it.next().map(|sel| {
+        let mut min = sel;
+        let mut max = sel;
+
+        for x in it {
+            if x < min {
+                min = x
+            };
+            if x > max {
+                max = x
+            };
+        }
+
+        (min, max)
+    })
 }
 
-pub fn heightmap_from_vec(width: u32, height: u32, data: Vec<f64>) -> Heightmap {
+pub fn flat(x: f64) -> f64 {
+    (x - 0.5) * (x - 0.5) * (if x < 0.5 { -2.0 } else { 2.0 }) + 0.5
+}
+
+pub fn heightmap_from_vec<T>(width: u32, height: u32, data: Vec<T>) -> Heightmap<T> {
     Heightmap {
         width,
         height,
@@ -100,9 +136,9 @@ pub fn heightmap_from_vec(width: u32, height: u32, data: Vec<f64>) -> Heightmap
     }
 }
 
-pub fn heightmap_from_iter<I>(width: u32, height: u32, iter: I) -> Heightmap
-    where I: Iterator<Item = f64>
+pub fn heightmap_from_iter<T, I>(width: u32, height: u32, iter: I) -> Heightmap<T>
+    where I: Iterator<Item = T>
 {
-    let vec: Vec<f64> = iter.collect();
+    let vec: Vec<T> = iter.collect();
     heightmap_from_vec(width, height, vec)
 }
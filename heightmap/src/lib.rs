@@ -1,8 +1,19 @@
+extern crate memmap;
 extern crate noise2d;
 extern crate rand;
 
+mod analysis;
+mod components;
+mod contour;
 mod generator2d;
 mod heightmap;
+mod mmap;
+mod sample;
+mod viewshed;
 
-pub use heightmap::Heightmap;
-pub use generator2d::{Generator2d, Diamond2d, Fractal2d, Midpoint2d};
+pub use components::{Component, Components};
+pub use contour::Contour;
+pub use heightmap::{Heightmap, Heightmap16, Heightmap32, heightmap_from_iter, heightmap_from_vec};
+pub use generator2d::{Generator2d, Diamond2d, Fractal2d, Midpoint2d, TileGenerator2d};
+pub use mmap::MmapHeightmap;
+pub use sample::Sample;
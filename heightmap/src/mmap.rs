@@ -0,0 +1,344 @@
+//! Heightmap stored in a memory-mapped file, for maps larger than the available memory.
+//!
+//! The map is split in square tiles stored one after the other, each tile being stored row by row,
+//! so that working on a tile only touches a contiguous part of the file. Tiles on the right and
+//! bottom edges are smaller when the size is not a multiple of the tile size: the file holds
+//! exactly `width * height` samples in their native representation.
+use memmap::{Mmap, Protection};
+use std::cmp::min;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::Range;
+use std::path::Path;
+use std::slice;
+
+use generator2d::TileGenerator2d;
+use heightmap::{Heightmap, flat, heightmap_from_iter, heightmap_from_vec, minmax};
+use sample::Sample;
+
+pub struct MmapHeightmap<T = f64> {
+    width: u32,
+    height: u32,
+    tile_size: u32,
+    mmap: Mmap,
+    sample: PhantomData<T>,
+}
+
+impl<T> MmapHeightmap<T>
+    where T: Sample
+{
+    /// Creates, or truncates, the file at `path` to hold a `width` x `height` map.
+    pub fn create<P>(path: P, width: u32, height: u32, tile_size: u32) -> io::Result<MmapHeightmap<T>>
+        where P: AsRef<Path>
+    {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len(Self::byte_len(width, height))?;
+
+        Self::map(&file, width, height, tile_size)
+    }
+
+    /// Maps a file previously filled through `create` with the same sizes.
+    pub fn open<P>(path: P, width: u32, height: u32, tile_size: u32) -> io::Result<MmapHeightmap<T>>
+        where P: AsRef<Path>
+    {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        if file.metadata()?.len() != Self::byte_len(width, height) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "File size does not match the map size"));
+        }
+
+        Self::map(&file, width, height, tile_size)
+    }
+
+    fn byte_len(width: u32, height: u32) -> u64 {
+        u64::from(width) * u64::from(height) * mem::size_of::<T>() as u64
+    }
+
+    fn map(file: &File, width: u32, height: u32, tile_size: u32) -> io::Result<MmapHeightmap<T>> {
+        if tile_size == 0 {
+            panic!("Tile size must be positive")
+        }
+
+        Ok(MmapHeightmap {
+               width,
+               height,
+               tile_size,
+               mmap: Mmap::open(file, Protection::ReadWrite)?,
+               sample: PhantomData,
+           })
+    }
+
+    fn data(&self) -> &[T] {
+        let len = self.width as usize * self.height as usize;
+        unsafe { slice::from_raw_parts(self.mmap.ptr() as *const T, len) }
+    }
+
+    fn data_mut(&mut self) -> &mut [T] {
+        let len = self.width as usize * self.height as usize;
+        unsafe { slice::from_raw_parts_mut(self.mmap.mut_ptr() as *mut T, len) }
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    /// Number of tiles along each axis.
+    pub fn tiles(&self) -> (u32, u32) {
+        (self.width.div_ceil(self.tile_size), self.height.div_ceil(self.tile_size))
+    }
+
+    /// Position and size of a tile, as `(x, y, width, height)`.
+    pub fn tile_bounds(&self, tx: u32, ty: u32) -> (u32, u32, u32, u32) {
+        let (tiles_x, tiles_y) = self.tiles();
+        if tx >= tiles_x || ty >= tiles_y {
+            panic!("Tile out of bounds: ({}, {}) not in {}x{}", tx, ty, tiles_x, tiles_y)
+        }
+
+        let x = tx * self.tile_size;
+        let y = ty * self.tile_size;
+
+        (x, y, min(self.tile_size, self.width - x), min(self.tile_size, self.height - y))
+    }
+
+    fn tile_range(&self, tx: u32, ty: u32) -> Range<usize> {
+        let (_, y, width, height) = self.tile_bounds(tx, ty);
+        let start = y as usize * self.width as usize + (tx * self.tile_size) as usize * height as usize;
+
+        start..start + width as usize * height as usize
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        let (_, _, width, _) = self.tile_bounds(x / self.tile_size, y / self.tile_size);
+        let range = self.tile_range(x / self.tile_size, y / self.tile_size);
+
+        range.start + (y % self.tile_size) as usize * width as usize + (x % self.tile_size) as usize
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> f64 {
+        self.data()[self.index(x, y)].to_f64()
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, value: f64) {
+        let i = self.index(x, y);
+        self.data_mut()[i] = T::from_f64(value);
+    }
+
+    /// Copies a tile in memory.
+    pub fn tile(&self, tx: u32, ty: u32) -> Heightmap<T> {
+        let (_, _, width, height) = self.tile_bounds(tx, ty);
+        let range = self.tile_range(tx, ty);
+
+        heightmap_from_vec(width, height, self.data()[range].to_vec())
+    }
+
+    /// Overwrites a tile with a map of the same size.
+    pub fn set_tile(&mut self, tx: u32, ty: u32, tile: &Heightmap<T>) {
+        let (_, _, width, height) = self.tile_bounds(tx, ty);
+        if tile.width() != width || tile.height() != height {
+            panic!("Tile size mismatch: {}x{} != {}x{}", tile.width(), tile.height(), width, height)
+        }
+
+        let range = self.tile_range(tx, ty);
+        for (d, s) in self.data_mut()[range].iter_mut().zip(tile.heights()) {
+            *d = *s;
+        }
+    }
+
+    /// Fills the map tile by tile, normalizing the values on the fly.
+    ///
+    /// The generator runs twice: once to find the range of the values and once to store them, so
+    /// that the raw values are never clamped by storage types such as `u16`.
+    pub fn generate<G>(&mut self, g: &G)
+        where G: TileGenerator2d
+    {
+        let (tiles_x, tiles_y) = self.tiles();
+        let tiles = (0..tiles_y).flat_map(|ty| (0..tiles_x).map(move |tx| (tx, ty))).collect::<Vec<_>>();
+
+        let range = tiles
+            .iter()
+            .filter_map(|&(tx, ty)| {
+                            let (x, y, width, height) = self.tile_bounds(tx, ty);
+                            let tile = g.generate_tile(self.width, self.height, x, y, width, height);
+                            minmax(tile.heights().cloned())
+                        })
+            .fold(None, |acc, (min, max)| match acc {
+                None => Some((min, max)),
+                Some((amin, amax)) => Some((min.min(amin), max.max(amax))),
+            });
+
+        if let Some((min, max)) = range {
+            for &(tx, ty) in &tiles {
+                let (x, y, width, height) = self.tile_bounds(tx, ty);
+                let tile = g.generate_tile(self.width, self.height, x, y, width, height);
+                let range = self.tile_range(tx, ty);
+
+                for (d, s) in self.data_mut()[range].iter_mut().zip(tile.heights()) {
+                    *d = T::from_f64((*s - min) / (max - min));
+                }
+            }
+        }
+    }
+
+    pub fn normalize(&mut self) {
+        if let Some((min, max)) = minmax(self.data().iter().map(|x| x.to_f64())) {
+            for x in self.data_mut().iter_mut() {
+                *x = T::from_f64((x.to_f64() - min) / (max - min));
+            }
+        }
+    }
+
+    pub fn flatten(&mut self) {
+        for x in self.data_mut().iter_mut() {
+            *x = T::from_f64(flat(x.to_f64()));
+        }
+    }
+
+    /// Copies any part of the map in memory.
+    pub fn submap(&self, x: u32, y: u32, width: u32, height: u32) -> Heightmap<T> {
+        if x + width > self.width {
+            panic!("Width out of bounds: {} + {} > {}", x, width, self.width)
+        }
+        if y + height > self.height {
+            panic!("Height out of bounds: {} + {} > {}", y, height, self.height)
+        }
+
+        heightmap_from_iter(width,
+                            height,
+                            (y..y + height).flat_map(|j| (x..x + width).map(move |i| self.data()[self.index(i, j)])))
+    }
+
+    /// Writes the pending changes to the file.
+    pub fn flush(&self) -> io::Result<()> {
+        self.mmap.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Neither side is a multiple of the tile size, so the last row and column of tiles are smaller.
+    const WIDTH: u32 = 11;
+    const HEIGHT: u32 = 7;
+    const TILE_SIZE: u32 = 4;
+
+    /// File in the temporary directory, removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> TempFile {
+            TempFile(env::temp_dir().join(format!("heightmap-mmap-{}-{}", name, ::std::process::id())))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    /// Distinct value for each sample.
+    fn value(x: u32, y: u32) -> f64 {
+        f64::from(y * WIDTH + x) / f64::from(WIDTH * HEIGHT)
+    }
+
+    fn filled(file: &TempFile) -> (MmapHeightmap, Heightmap) {
+        let mut mmap = MmapHeightmap::create(&file.0, WIDTH, HEIGHT, TILE_SIZE).unwrap();
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                mmap.set(x, y, value(x, y));
+            }
+        }
+        let hmap = heightmap_from_iter(WIDTH,
+                                       HEIGHT,
+                                       (0..WIDTH * HEIGHT).map(|i| value(i % WIDTH, i / WIDTH)));
+
+        (mmap, hmap)
+    }
+
+    fn assert_same(a: &Heightmap, b: &Heightmap) {
+        assert_eq!((a.width(), a.height()), (b.width(), b.height()));
+        for y in 0..a.height() {
+            for x in 0..a.width() {
+                assert_eq!(a.get(x, y), b.get(x, y), "sample ({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn samples_round_trip_across_edge_tiles() {
+        let file = TempFile::new("round-trip");
+        let (mmap, _) = filled(&file);
+
+        assert_eq!(mmap.tiles(), (3, 2));
+        assert_eq!(mmap.tile_bounds(2, 1), (8, 4, 3, 3));
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                assert_eq!(mmap.get(x, y), value(x, y), "sample ({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn tiles_and_submaps_match_the_map_in_memory() {
+        let file = TempFile::new("tiles");
+        let (mut mmap, hmap) = filled(&file);
+
+        let (tiles_x, tiles_y) = mmap.tiles();
+        for ty in 0..tiles_y {
+            for tx in 0..tiles_x {
+                let (x, y, width, height) = mmap.tile_bounds(tx, ty);
+                assert_same(&mmap.tile(tx, ty), &hmap.submap(x, y, width, height));
+            }
+        }
+        assert_same(&mmap.submap(3, 2, 7, 5), &hmap.submap(3, 2, 7, 5));
+        assert_same(&mmap.submap(0, 0, WIDTH, HEIGHT), &hmap);
+
+        // Overwriting a tile only changes its own samples
+        let (x, y, width, height) = mmap.tile_bounds(2, 0);
+        let tile = heightmap_from_iter(width, height, (0..width * height).map(|i| 1.0 + f64::from(i)));
+        mmap.set_tile(2, 0, &tile);
+        for v in 0..HEIGHT {
+            for u in 0..WIDTH {
+                let expected = if u >= x && v >= y && u < x + width && v < y + height {
+                    tile.get(u - x, v - y)
+                } else {
+                    value(u, v)
+                };
+                assert_eq!(mmap.get(u, v), expected, "sample ({}, {})", u, v);
+            }
+        }
+    }
+
+    #[test]
+    fn opened_files_keep_their_samples() {
+        let file = TempFile::new("open");
+        let (mmap, hmap) = filled(&file);
+        mmap.flush().unwrap();
+        drop(mmap);
+
+        let opened = MmapHeightmap::<f64>::open(&file.0, WIDTH, HEIGHT, TILE_SIZE).unwrap();
+        assert_same(&opened.submap(0, 0, WIDTH, HEIGHT), &hmap);
+
+        let error = MmapHeightmap::<f64>::open(&file.0, WIDTH + 1, HEIGHT, TILE_SIZE).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(MmapHeightmap::<f32>::open(&file.0, WIDTH, HEIGHT, TILE_SIZE).is_err());
+    }
+}
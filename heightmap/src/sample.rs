@@ -0,0 +1,52 @@
+//! Storage types for the values of a `Heightmap`.
+
+/// A value that can be stored in a `Heightmap`.
+///
+/// Every computation is done in `f64`, samples are only converted when read or written.
+pub trait Sample: Copy {
+    fn from_f64(v: f64) -> Self;
+    fn to_f64(self) -> f64;
+
+    /// Whether values outside of `[0, 1]` are clamped when stored, raw values then having to be
+    /// normalized before they are.
+    fn clamps() -> bool {
+        false
+    }
+}
+
+impl Sample for f64 {
+    fn from_f64(v: f64) -> Self {
+        v
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+impl Sample for f32 {
+    fn from_f64(v: f64) -> Self {
+        v as f32
+    }
+
+    fn to_f64(self) -> f64 {
+        f64::from(self)
+    }
+}
+
+/// Normalized 16-bit sample: `0` maps to `0.0` and `u16::MAX` to `1.0`.
+///
+/// Values outside of `[0, 1]` are clamped, so the map should be normalized before conversion.
+impl Sample for u16 {
+    fn from_f64(v: f64) -> Self {
+        (v.clamp(0.0, 1.0) * f64::from(u16::MAX)).round() as u16
+    }
+
+    fn to_f64(self) -> f64 {
+        f64::from(self) / f64::from(u16::MAX)
+    }
+
+    fn clamps() -> bool {
+        true
+    }
+}
@@ -0,0 +1,68 @@
+//! Line of sight analysis.
+//!
+//! Rays are cast from the observer to each sample of the borders of the map, a sample being visible
+//! when it rises above everything between it and the observer along one of the rays (R2).
+use heightmap::{Heightmap, heightmap_from_vec};
+use sample::Sample;
+
+impl<T> Heightmap<T>
+    where T: Sample
+{
+    /// `1.0` for the samples seen by an observer whose eyes are `height` above the sample at
+    /// `observer`, `0.0` for the hidden ones. `scale` is the height of a sample at `1.0`, in
+    /// distances between two samples.
+    pub fn viewshed(&self, observer: (u32, u32), height: f64, scale: f64) -> Heightmap {
+        let (width, rows) = (self.width(), self.height());
+        let mut visible = vec![0.0; width as usize * rows as usize];
+        let eye = (self.get(observer.0, observer.1) + height) * scale;
+        visible[observer.1 as usize * width as usize + observer.0 as usize] = 1.0;
+
+        let borders = (0..width)
+            .flat_map(|x| vec![(x, 0), (x, rows - 1)])
+            .chain((1..rows.saturating_sub(1)).flat_map(|y| vec![(0, y), (width - 1, y)]));
+        for (tx, ty) in borders {
+            let (dx, dy) = (f64::from(tx) - f64::from(observer.0), f64::from(ty) - f64::from(observer.1));
+            let steps = dx.abs().max(dy.abs()) as u32;
+
+            let mut horizon = ::std::f64::NEG_INFINITY;
+            for i in 1..steps + 1 {
+                let t = f64::from(i) / f64::from(steps);
+                let (px, py) = (f64::from(observer.0) + dx * t, f64::from(observer.1) + dy * t);
+                let (x, y) = (px.round() as u32, py.round() as u32);
+
+                // The sample crossed is tested at its center, the horizon following the ray between
+                // the samples
+                let (cx, cy) = (f64::from(x) - f64::from(observer.0), f64::from(y) - f64::from(observer.1));
+                if (self.get(x, y) * scale - eye) / cx.hypot(cy) >= horizon {
+                    visible[y as usize * width as usize + x as usize] = 1.0;
+                }
+                horizon = horizon.max((self.interpolate(px, py) * scale - eye) / (dx * t).hypot(dy * t));
+            }
+        }
+
+        heightmap_from_vec(width, rows, visible)
+    }
+
+    /// Height between the samples, interpolated bilinearly from the four nearest ones.
+    pub fn interpolate(&self, x: f64, y: f64) -> f64 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (x1, y1) = ((x0 + 1.0).min(f64::from(self.width() - 1)), (y0 + 1.0).min(f64::from(self.height() - 1)));
+        let (fx, fy) = (x - x0, y - y0);
+
+        (self.get(x0 as u32, y0 as u32) * (1.0 - fx) + self.get(x1 as u32, y0 as u32) * fx) * (1.0 - fy) +
+        (self.get(x0 as u32, y1 as u32) * (1.0 - fx) + self.get(x1 as u32, y1 as u32) * fx) * fy
+    }
+
+    /// Number of the observers seeing each sample, all of them having their eyes `height` above
+    /// the ground.
+    pub fn cumulative_viewshed(&self, observers: &[(u32, u32)], height: f64, scale: f64) -> Heightmap {
+        let mut counts = vec![0.0; self.width() as usize * self.height() as usize];
+        for &observer in observers {
+            for (count, &seen) in counts.iter_mut().zip(self.viewshed(observer, height, scale).heights()) {
+                *count += seen;
+            }
+        }
+
+        heightmap_from_vec(self.width(), self.height(), counts)
+    }
+}
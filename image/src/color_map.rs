@@ -0,0 +1,242 @@
+//! Associate a `Color` to a pair of values, such as the height and the moisture.
+use color::{Color, lerp};
+use heightmap::{Heightmap, Sample};
+use image::{ImageBuffer, Rgb, RgbImage};
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::Error;
+use std::cmp::Ordering;
+use std::vec::Vec;
+
+/// Colors of a row of the grid, for the second value at `value`.
+#[derive(Clone, Debug)]
+struct ColorRow {
+    value: f64,
+    colors: Vec<Color>,
+}
+
+impl Serialize for ColorRow {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        let mut string = format!("{:.8}", self.value);
+        for color in &self.colors {
+            string += &format!("  {: >3} {: >3} {: >3}", color.red(), color.green(), color.blue());
+        }
+
+        serializer.serialize_str(&string)
+    }
+}
+
+impl Deserialize for ColorRow {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer
+    {
+        let string = String::deserialize(deserializer)?;
+        let mut iter = string.split_whitespace();
+
+        let value = iter.next()
+            .map(|s| s.parse().map_err(D::Error::custom))
+            .unwrap_or_else(||Err(D::Error::custom("missing value")))?;
+
+        let components = iter.map(|s| s.parse().map_err(D::Error::custom))
+            .collect::<Result<Vec<u8>, _>>()?;
+        if components.len() % 3 != 0 {
+            return Err(D::Error::custom("incomplete color"));
+        }
+
+        Ok(ColorRow {
+               value,
+               colors: components.chunks(3).map(|c| Color::new([c[0], c[1], c[2]])).collect(),
+           })
+    }
+}
+
+#[derive(Deserialize)]
+struct ColorGrid {
+    columns: Vec<f64>,
+    rows: Vec<ColorRow>,
+}
+
+/// Grid of colors interpolated bilinearly: the first value selects the column and the second one
+/// the row.
+///
+/// ```yaml
+/// columns: [0.0, 1.0]
+/// rows:
+///   - "0.0   210 190 140   30 110  40"
+///   - "1.0   255 255 255  240 240 255"
+/// ```
+#[derive(Clone, Serialize, Debug)]
+pub struct ColorMap {
+    columns: Vec<f64>,
+    rows: Vec<ColorRow>,
+}
+
+impl Deserialize for ColorMap {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer
+    {
+        let grid = ColorGrid::deserialize(deserializer)?;
+        if grid.columns.is_empty() || grid.rows.is_empty() {
+            return Err(D::Error::custom("empty color map"));
+        }
+        if grid.rows.iter().any(|r| r.colors.len() != grid.columns.len()) {
+            return Err(D::Error::custom("each row needs one color per column"));
+        }
+
+        Ok(ColorMap::new(grid.columns,
+                         grid.rows.into_iter().map(|r| (r.value, r.colors)).collect()))
+    }
+}
+
+impl ColorMap {
+    /// Create a map, `columns` being the values of the first field of each column and `rows` the
+    /// values of the second field with the colors of each column.
+    ///
+    /// # Panics
+    ///
+    /// If there are no columns or no rows, or if a row has not one color per column.
+    pub fn new(columns: Vec<f64>, rows: Vec<(f64, Vec<Color>)>) -> ColorMap {
+        assert!(!columns.is_empty() && !rows.is_empty(), "empty color map");
+
+        // Columns are sorted along with the colors of each row
+        let mut order = (0..columns.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| columns[a].partial_cmp(&columns[b]).unwrap_or(Ordering::Equal));
+
+        let mut map = ColorMap {
+            columns: order.iter().map(|&i| columns[i]).collect(),
+            rows: Vec::new(),
+        };
+        for (value, colors) in rows {
+            assert_eq!(colors.len(), order.len());
+            map.add_row(value, order.iter().map(|&i| colors[i]).collect());
+        }
+
+        map
+    }
+
+    /// Map whose colors are the pixels of a palette, both values going from `0` at the top left to
+    /// `1` at the bottom right.
+    ///
+    /// # Panics
+    ///
+    /// If the palette is empty.
+    pub fn from_image(palette: &RgbImage) -> ColorMap {
+        let step = |i: u32, n: u32| if n > 1 { f64::from(i) / f64::from(n - 1) } else { 0.0 };
+        let (width, height) = palette.dimensions();
+
+        let rows = (0..height)
+            .map(|j| {
+                let colors = (0..width)
+                    .map(|i| {
+                        let p = palette.get_pixel(i, j);
+                        Color::new([p.data[0], p.data[1], p.data[2]])
+                    })
+                    .collect();
+                (step(j, height), colors)
+            })
+            .collect();
+
+        ColorMap::new((0..width).map(|i| step(i, width)).collect(), rows)
+    }
+
+    /// Add the colors of each column for the second field at `value`.
+    ///
+    /// # Panics
+    ///
+    /// If there is not one color per column.
+    pub fn add_row(&mut self, value: f64, colors: Vec<Color>) {
+        assert_eq!(colors.len(), self.columns.len());
+        let r = ColorRow { value, colors };
+
+        match self.rows.iter().position(|ref x| x.value >= r.value) {
+            Some(i) => self.rows.insert(i, r),
+            None => self.rows.push(r),
+        }
+    }
+
+    pub fn get(&self, x: f64, y: f64) -> Color {
+        let (i0, i1, s) = locate(self.columns.len(), |i| self.columns[i], x);
+        let (j0, j1, t) = locate(self.rows.len(), |j| self.rows[j].value, y);
+
+        let top = lerp(self.rows[j0].colors[i0], self.rows[j0].colors[i1], s);
+        let bottom = lerp(self.rows[j1].colors[i0], self.rows[j1].colors[i1], s);
+        lerp(top, bottom, t)
+    }
+
+    /// Colors each sample by the values of both maps.
+    ///
+    /// # Panics
+    ///
+    /// If the maps are not the same size.
+    pub fn apply_on<T, U>(&self, x: &Heightmap<T>, y: &Heightmap<U>) -> ImageBuffer<Rgb<u8>, Vec<u8>>
+        where T: Sample,
+              U: Sample
+    {
+        assert_eq!((x.width(), x.height()), (y.width(), y.height()), "maps of different sizes");
+        ImageBuffer::from_fn(x.width(), x.height(), |i, j| *self.get(x.get(i, j), y.get(i, j)))
+    }
+}
+
+/// Indices of the steps around `pos`, and the position between them.
+fn locate<F>(len: usize, step: F, pos: f64) -> (usize, usize, f64)
+    where F: Fn(usize) -> f64
+{
+    match (0..len).position(|i| step(i) >= pos) {
+        None => (len - 1, len - 1, 0.0),
+        Some(0) => (0, 0, 0.0),
+        Some(i) => (i - 1, i, (pos - step(i - 1)) / (step(i) - step(i - 1))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use heightmap::heightmap_from_vec;
+
+    fn grey(level: u8) -> Color {
+        Color::new([level, level, level])
+    }
+
+    fn map() -> ColorMap {
+        ColorMap::new(vec![0.0, 1.0],
+                      vec![(0.0, vec![grey(0), grey(100)]), (1.0, vec![grey(100), grey(200)])])
+    }
+
+    #[test]
+    fn steps_around_a_position() {
+        let steps = [0.0, 0.5, 2.0];
+        assert_eq!(locate(3, |i| steps[i], 0.25), (0, 1, 0.5));
+        assert_eq!(locate(3, |i| steps[i], 1.25), (1, 2, 0.5));
+        assert_eq!(locate(3, |i| steps[i], 0.5), (0, 1, 1.0));
+        assert_eq!(locate(3, |i| steps[i], -1.0), (0, 0, 0.0));
+        assert_eq!(locate(3, |i| steps[i], 3.0), (2, 2, 0.0));
+    }
+
+    #[test]
+    fn colors_are_interpolated_bilinearly() {
+        let map = map();
+        assert_eq!(*map.get(0.0, 0.0), *grey(0));
+        assert_eq!(*map.get(1.0, 1.0), *grey(200));
+        assert_eq!(*map.get(0.5, 0.0), *grey(50));
+        assert_eq!(*map.get(0.0, 0.5), *grey(50));
+        assert_eq!(*map.get(0.5, 0.5), *grey(100));
+    }
+
+    #[test]
+    fn values_outside_of_the_grid_are_clamped() {
+        let map = map();
+        assert_eq!(*map.get(-1.0, -1.0), *grey(0));
+        assert_eq!(*map.get(2.0, -1.0), *grey(100));
+        assert_eq!(*map.get(2.0, 2.0), *grey(200));
+        assert_eq!(*map.get(0.5, 2.0), *grey(150));
+    }
+
+    #[test]
+    #[should_panic(expected = "maps of different sizes")]
+    fn maps_of_different_sizes_are_rejected() {
+        let x = heightmap_from_vec(2, 1, vec![0.0, 1.0]);
+        let y = heightmap_from_vec(1, 2, vec![0.0, 1.0]);
+        map().apply_on(&x, &y);
+    }
+}
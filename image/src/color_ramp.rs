@@ -1,6 +1,6 @@
 //! Associate a `Color` to a value.
 use color::{Color, lerp};
-use heightmap::Heightmap;
+use heightmap::{Heightmap, Sample};
 use image::{ImageBuffer, Rgb};
 use serde::{Serialize, Serializer, Deserialize, Deserializer};
 use serde::de::Error;
@@ -95,7 +95,9 @@ impl ColorRamp {
         }
     }
 
-    pub fn apply_on(&self, map: &Heightmap) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    pub fn apply_on<T>(&self, map: &Heightmap<T>) -> ImageBuffer<Rgb<u8>, Vec<u8>>
+        where T: Sample
+    {
         ImageBuffer::from_fn(map.width(), map.height(), |x, y| *self.get(map.get(x, y)))
     }
 }
@@ -0,0 +1,125 @@
+//! Write the raw values of a `Heightmap` for game engines and other tools.
+use heightmap::{Heightmap, Sample};
+use image::{ColorType, RgbImage};
+use image::png::PNGEncoder;
+use std::io;
+use std::io::Write;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+pub trait Export {
+    /// 16-bit grayscale PNG, the map is expected to be normalized.
+    fn write_png16<W: Write>(&self, w: W) -> io::Result<()>;
+
+    /// Headerless 16-bit samples, row by row (`.r16` / `.raw` files).
+    fn write_r16<W: Write>(&self, w: W, endianness: Endianness) -> io::Result<()>;
+
+    /// Headerless little-endian 32-bit floats, row by row.
+    fn write_f32<W: Write>(&self, w: W) -> io::Result<()>;
+}
+
+impl<T> Export for Heightmap<T>
+    where T: Sample
+{
+    fn write_png16<W: Write>(&self, w: W) -> io::Result<()> {
+        let mut data = Vec::with_capacity(self.width() as usize * self.height() as usize * 2);
+        for h in self.heights() {
+            data.extend_from_slice(&u16::from_f64(h.to_f64()).to_be_bytes());
+        }
+
+        PNGEncoder::new(w).encode(&data, self.width(), self.height(), ColorType::Gray(16))
+    }
+
+    fn write_r16<W: Write>(&self, mut w: W, endianness: Endianness) -> io::Result<()> {
+        for h in self.heights() {
+            let v = u16::from_f64(h.to_f64());
+            match endianness {
+                Endianness::Little => w.write_all(&v.to_le_bytes())?,
+                Endianness::Big => w.write_all(&v.to_be_bytes())?,
+            }
+        }
+
+        w.flush()
+    }
+
+    fn write_f32<W: Write>(&self, mut w: W) -> io::Result<()> {
+        for h in self.heights() {
+            w.write_all(&f32::from_f64(h.to_f64()).to_le_bytes())?;
+        }
+
+        w.flush()
+    }
+}
+
+/// Encodes a rendered map as PNG in memory, to embed it in other files.
+pub fn png_bytes(img: &RgbImage) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    PNGEncoder::new(&mut bytes).encode(img, img.width(), img.height(), ColorType::RGB(8))?;
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use heightmap::heightmap_from_iter;
+    use import::{read_f32, read_png, read_r16};
+
+    const WIDTH: u32 = 7;
+    const HEIGHT: u32 = 5;
+
+    /// Normalized ramp covering both ends of the range, with values between two 16-bit steps.
+    fn known() -> Heightmap {
+        let last = f64::from(WIDTH * HEIGHT - 1);
+        heightmap_from_iter(WIDTH, HEIGHT, (0..WIDTH * HEIGHT).map(|i| (f64::from(i) / last).powf(1.7)))
+    }
+
+    fn assert_close(a: &Heightmap, b: &Heightmap, step: f64) {
+        assert_eq!((a.width(), a.height()), (b.width(), b.height()));
+        for (x, y) in a.heights().zip(b.heights()) {
+            assert!((x - y).abs() <= step, "{} and {} differ by more than {}", x, y, step);
+        }
+    }
+
+    #[test]
+    fn png16_round_trip() {
+        let mut bytes = Vec::new();
+        known().write_png16(&mut bytes).unwrap();
+
+        let read = read_png(&bytes[..]).unwrap();
+        assert_close(&known(), &read, 0.5 / f64::from(u16::MAX));
+    }
+
+    #[test]
+    fn r16_little_endian_round_trip() {
+        let mut bytes = Vec::new();
+        known().write_r16(&mut bytes, Endianness::Little).unwrap();
+        assert_eq!(bytes.len(), (WIDTH * HEIGHT * 2) as usize);
+
+        let read = read_r16(&bytes[..], WIDTH, HEIGHT, Endianness::Little).unwrap();
+        assert_close(&known(), &read, 0.5 / f64::from(u16::MAX));
+    }
+
+    #[test]
+    fn r16_big_endian_round_trip() {
+        let mut bytes = Vec::new();
+        known().write_r16(&mut bytes, Endianness::Big).unwrap();
+
+        let read = read_r16(&bytes[..], WIDTH, HEIGHT, Endianness::Big).unwrap();
+        assert_close(&known(), &read, 0.5 / f64::from(u16::MAX));
+    }
+
+    #[test]
+    fn f32_round_trip() {
+        let mut bytes = Vec::new();
+        known().write_f32(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), (WIDTH * HEIGHT * 4) as usize);
+
+        let read = read_f32(&bytes[..], WIDTH, HEIGHT).unwrap();
+        assert_close(&known(), &read, f64::from(::std::f32::EPSILON));
+    }
+}
@@ -0,0 +1,594 @@
+//! Maps drawn by hand: ink on parchment, with hatched coasts, and mountains and forests drawn as
+//! glyphs.
+use color::{Color, lerp};
+use heightmap::{Heightmap, Sample, heightmap_from_vec};
+use image::RgbImage;
+use line::trace_line;
+use noise2d::Noise2d;
+use overlay::overlay;
+use std::cmp::Ordering;
+use std::f64::consts::{PI, SQRT_2};
+
+/// Style of a map drawn on parchment, used instead of the color ramp and the shading:
+///
+/// ```yaml
+/// fantasy:
+///   mountain_size: 16
+///   forests: [taiga, temperate forest]
+///   compass: false
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FantasyOptions {
+    /// Colour of the paper on land.
+    #[serde(default = "default_paper")]
+    pub paper: Color,
+    /// Colour of the paper at sea.
+    #[serde(default = "default_sea")]
+    pub sea: Color,
+    /// Colour of the lines and the glyphs.
+    #[serde(default = "default_ink")]
+    pub ink: Color,
+    /// Colour of the rivers.
+    #[serde(default = "default_river_ink")]
+    pub river_ink: Color,
+    /// Darkening of the stains and the edges of the paper, `0` for a clean sheet.
+    #[serde(default = "default_stains")]
+    pub stains: f64,
+    /// Width of the coastline, in pixels.
+    #[serde(default = "default_coast_width")]
+    pub coast_width: f64,
+    /// How far the hatching goes out to sea from the coast, in pixels, `0` to disable.
+    #[serde(default = "default_hatch_length")]
+    pub hatch_length: f64,
+    /// Distance between two hatches, in pixels.
+    #[serde(default = "default_hatch_spacing")]
+    pub hatch_spacing: f64,
+    /// Height of a sample at `1.0`, in distances between two samples.
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    /// Land steeper than this, in degrees, is drawn as mountains.
+    #[serde(default = "default_mountain_slope")]
+    pub mountain_slope: f64,
+    /// Land higher than this is drawn as mountains.
+    #[serde(default = "default_mountain_height")]
+    pub mountain_height: f64,
+    /// Width of a mountain, in pixels, the mountains being about as far apart.
+    #[serde(default = "default_mountain_size")]
+    pub mountain_size: f64,
+    /// Width of a tree, in pixels, the trees being about as far apart.
+    #[serde(default = "default_tree_size")]
+    pub tree_size: f64,
+    /// Biomes drawn as forests when the map has biomes, forests being scattered over the lowlands
+    /// otherwise.
+    #[serde(default = "default_forests")]
+    pub forests: Vec<String>,
+    /// Share of the map draining through a sample for it to be drawn as a river, `0` to disable.
+    #[serde(default = "default_river")]
+    pub river: f64,
+    /// Width of the rivers, in pixels.
+    #[serde(default = "default_river_width")]
+    pub river_width: f64,
+    /// Whether to draw a compass rose in the top right corner.
+    #[serde(default = "default_compass")]
+    pub compass: bool,
+    /// Diameter of the compass rose, as a share of the smaller side of the map.
+    #[serde(default = "default_compass_size")]
+    pub compass_size: f64,
+}
+
+fn default_paper() -> Color {
+    Color::new([236, 222, 182])
+}
+
+fn default_sea() -> Color {
+    Color::new([214, 210, 180])
+}
+
+fn default_ink() -> Color {
+    Color::new([72, 52, 34])
+}
+
+fn default_river_ink() -> Color {
+    Color::new([58, 80, 112])
+}
+
+fn default_stains() -> f64 {
+    0.2
+}
+
+fn default_coast_width() -> f64 {
+    1.5
+}
+
+fn default_hatch_length() -> f64 {
+    10.0
+}
+
+fn default_hatch_spacing() -> f64 {
+    3.0
+}
+
+fn default_scale() -> f64 {
+    50.0
+}
+
+fn default_mountain_slope() -> f64 {
+    30.0
+}
+
+fn default_mountain_height() -> f64 {
+    0.75
+}
+
+fn default_mountain_size() -> f64 {
+    14.0
+}
+
+fn default_tree_size() -> f64 {
+    6.0
+}
+
+fn default_forests() -> Vec<String> {
+    ["taiga", "temperate rainforest", "temperate forest", "tropical rainforest"]
+        .iter()
+        .map(|name| name.to_string())
+        .collect()
+}
+
+fn default_river() -> f64 {
+    0.0005
+}
+
+fn default_river_width() -> f64 {
+    1.2
+}
+
+fn default_compass() -> bool {
+    true
+}
+
+fn default_compass_size() -> f64 {
+    0.15
+}
+
+impl Default for FantasyOptions {
+    fn default() -> FantasyOptions {
+        FantasyOptions {
+            paper: default_paper(),
+            sea: default_sea(),
+            ink: default_ink(),
+            river_ink: default_river_ink(),
+            stains: default_stains(),
+            coast_width: default_coast_width(),
+            hatch_length: default_hatch_length(),
+            hatch_spacing: default_hatch_spacing(),
+            scale: default_scale(),
+            mountain_slope: default_mountain_slope(),
+            mountain_height: default_mountain_height(),
+            mountain_size: default_mountain_size(),
+            tree_size: default_tree_size(),
+            forests: default_forests(),
+            river: default_river(),
+            river_width: default_river_width(),
+            compass: default_compass(),
+            compass_size: default_compass_size(),
+        }
+    }
+}
+
+/// Glyph standing on the map at `(x, y)`, `size` pixels wide.
+enum Glyph {
+    Mountain(f64, f64, f64),
+    Tree(f64, f64, f64),
+}
+
+impl Glyph {
+    fn y(&self) -> f64 {
+        match *self {
+            Glyph::Mountain(_, y, _) | Glyph::Tree(_, y, _) => y,
+        }
+    }
+}
+
+impl FantasyOptions {
+    /// Draws the map, the samples below `sea_level` being under water and `forests` giving the
+    /// density of the trees from `0` to `1` on each sample. The stains, the hatches and the glyphs
+    /// vary with `noise`, whose values are from `0` to `1`.
+    pub fn render<T, N>(&self,
+                        hmap: &Heightmap<T>,
+                        sea_level: f64,
+                        noise: &N,
+                        forests: Option<&Heightmap>)
+                        -> RgbImage
+        where T: Sample,
+              N: Noise2d
+    {
+        let (width, height) = (hmap.width(), hmap.height());
+        let paper = self.paper(hmap, sea_level, noise);
+        let mut img = paper.clone();
+
+        if self.hatch_length > 0.0 && self.hatch_spacing > 0.0 {
+            overlay(&mut img, &self.hatching(hmap, sea_level, noise), self.ink, 0.8);
+        }
+
+        // Lines the glyphs keep clear of
+        let mut lines = blank(width, height);
+        for contour in hmap.contour(sea_level) {
+            trace_line(&mut lines, &contour.points, contour.closed, self.coast_width);
+        }
+        overlay(&mut img, &lines, self.ink, 1.0);
+
+        if self.river > 0.0 {
+            let mut rivers = blank(width, height);
+            let threshold = self.river * f64::from(width) * f64::from(height);
+            for course in hmap.rivers(threshold, sea_level) {
+                let points: Vec<_> = course.iter().map(|&(x, y)| (f64::from(x), f64::from(y))).collect();
+                trace_line(&mut rivers, &smooth(&points), false, self.river_width);
+            }
+            overlay(&mut img, &rivers, self.river_ink, 1.0);
+            for (x, y) in (0..height).flat_map(|y| (0..width).map(move |x| (x, y))) {
+                lines.set(x, y, lines.get(x, y).max(rivers.get(x, y)));
+            }
+        }
+
+        // From the back to the front, the glyphs below hiding the ones above
+        let mut glyphs = self.glyphs(hmap, sea_level, noise, forests, &lines);
+        glyphs.sort_by(|a, b| a.y().partial_cmp(&b.y()).unwrap_or(Ordering::Equal));
+        for glyph in &glyphs {
+            match *glyph {
+                Glyph::Mountain(x, y, size) => self.draw_mountain(&mut img, &paper, noise, x, y, size),
+                Glyph::Tree(x, y, size) => self.draw_tree(&mut img, &paper, x, y, size),
+            }
+        }
+
+        if self.compass && self.compass_size > 0.0 {
+            self.draw_compass(&mut img, &paper);
+        }
+
+        img
+    }
+
+    /// Paper stained in places and darkened towards its edges, tinted at sea.
+    fn paper<T, N>(&self, hmap: &Heightmap<T>, sea_level: f64, noise: &N) -> RgbImage
+        where T: Sample,
+              N: Noise2d
+    {
+        let (width, height) = (f64::from(hmap.width()), f64::from(hmap.height()));
+        RgbImage::from_fn(hmap.width(), hmap.height(), |x, y| {
+            let (fx, fy) = (f64::from(x), f64::from(y));
+            let stain = 0.5 * value_noise(noise, fx, fy, 96.0, 1) + 0.3 * value_noise(noise, fx, fy, 24.0, 2) +
+                        0.2 * value_noise(noise, fx, fy, 4.0, 3);
+            let (u, v) = (2.0 * fx / width - 1.0, 2.0 * fy / height - 1.0);
+            let edge = u.abs().max(v.abs()).powi(6);
+            let darkness = self.stains * (((stain - 0.4) / 0.6).max(0.0) + edge);
+
+            let color = if hmap.get(x, y) < sea_level { self.sea } else { self.paper };
+            *lerp(color, self.ink, darkness)
+        })
+    }
+
+    /// Coverage of the diagonal hatches going out to sea from the coast, each reaching its own
+    /// distance.
+    fn hatching<T, N>(&self, hmap: &Heightmap<T>, sea_level: f64, noise: &N) -> Heightmap
+        where T: Sample,
+              N: Noise2d
+    {
+        let distances = self.coast_distances(hmap, sea_level);
+        let period = self.hatch_spacing * SQRT_2;
+        let data = (0..hmap.height())
+            .flat_map(|y| (0..hmap.width()).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                // The coast lies about halfway between land and water
+                let distance = distances.get(x, y) - 0.5;
+                if distance <= 0.0 || distance > self.hatch_length {
+                    return 0.0;
+                }
+
+                let diagonal = f64::from(x) + f64::from(y);
+                let line = (diagonal / period).round();
+                let across = (diagonal - line * period).abs() / SQRT_2;
+                let along = (f64::from(x) - f64::from(y)) / SQRT_2;
+                let reach = self.hatch_length * (0.3 + 0.7 * value_noise(noise, along, line * 8.0, 8.0, 4));
+
+                (0.85 - across).max(0.0).min(1.0) * (reach - distance).max(0.0).min(1.0)
+            })
+            .collect();
+
+        heightmap_from_vec(hmap.width(), hmap.height(), data)
+    }
+
+    /// Distance from each sample to the nearest land, passing on the nearest land sample found over
+    /// a forward and a backward sweep of the map.
+    fn coast_distances<T>(&self, hmap: &Heightmap<T>, sea_level: f64) -> Heightmap
+        where T: Sample
+    {
+        let (width, height) = (i64::from(hmap.width()), i64::from(hmap.height()));
+        let mut nearest: Vec<Option<(i64, i64)>> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| if hmap.get(x as u32, y as u32) >= sea_level { Some((x, y)) } else { None })
+            .collect();
+        let distance = |x: i64, y: i64, n: Option<(i64, i64)>| {
+            n.map_or(::std::f64::INFINITY, |(u, v)| ((x - u) as f64).hypot((y - v) as f64))
+        };
+
+        let forward = [(-1, -1), (0, -1), (1, -1), (-1, 0)];
+        let backward = [(1, 0), (-1, 1), (0, 1), (1, 1)];
+        let positions: Vec<_> = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).collect();
+        let sweeps = positions.iter().map(|p| (p, &forward)).chain(positions.iter().rev().map(|p| (p, &backward)));
+        for (&(x, y), offsets) in sweeps {
+            let i = (y * width + x) as usize;
+            for &(dx, dy) in offsets.iter() {
+                let (u, v) = (x + dx, y + dy);
+                if u >= 0 && v >= 0 && u < width && v < height {
+                    let candidate = nearest[(v * width + u) as usize];
+                    if distance(x, y, candidate) < distance(x, y, nearest[i]) {
+                        nearest[i] = candidate;
+                    }
+                }
+            }
+        }
+
+        let data = positions.iter().zip(nearest).map(|(&(x, y), n)| distance(x, y, n)).collect();
+        heightmap_from_vec(hmap.width(), hmap.height(), data)
+    }
+
+    /// Mountains on steep or high land and trees in the forests, on jittered grids, away from the
+    /// `lines` drawn.
+    fn glyphs<T, N>(&self,
+                    hmap: &Heightmap<T>,
+                    sea_level: f64,
+                    noise: &N,
+                    forests: Option<&Heightmap>,
+                    lines: &Heightmap)
+                    -> Vec<Glyph>
+        where T: Sample,
+              N: Noise2d
+    {
+        let slope = hmap.slope(self.scale);
+        let (width, height) = (f64::from(hmap.width()), f64::from(hmap.height()));
+        let at = |x: f64, y: f64| {
+            if x < 0.0 || y < 0.0 || x > width - 1.0 || y > height - 1.0 {
+                None
+            } else {
+                Some((x.round() as u32, y.round() as u32))
+            }
+        };
+        let is_land = |x: f64, y: f64| at(x, y).map_or(false, |(u, v)| hmap.get(u, v) >= sea_level);
+        let is_clear = |x: f64, y: f64| at(x, y).map_or(false, |(u, v)| lines.get(u, v) == 0.0);
+        let is_mountain = |x: f64, y: f64| {
+            at(x, y).map_or(false, |(u, v)| {
+                is_land(x, y) && (slope.get(u, v) >= self.mountain_slope || hmap.get(u, v) >= self.mountain_height)
+            })
+        };
+        let mut glyphs = Vec::new();
+
+        // Mountains overlap, rows being closer than columns
+        for (x, y, i, j) in jittered(noise, width, height, 1.2 * self.mountain_size, 0.6 * self.mountain_size, 5) {
+            let half = self.mountain_size / 2.0;
+            let footprint = [(x, y), (x - half, y), (x + half, y)];
+            if is_mountain(x, y) && footprint.iter().all(|&(u, v)| is_land(u, v) && is_clear(u, v)) {
+                let rise = at(x, y).map_or(0.0, |(u, v)| {
+                    ((hmap.get(u, v) - sea_level) / (1.0 - sea_level)).max(0.0).min(1.0)
+                });
+                let size = self.mountain_size * (0.6 + 0.6 * rise) * (0.9 + 0.2 * hash(noise, i, j, 6));
+                glyphs.push(Glyph::Mountain(x, y, size));
+            }
+        }
+
+        for (x, y, i, j) in jittered(noise, width, height, 1.3 * self.tree_size, self.tree_size, 7) {
+            let density = match (at(x, y), forests) {
+                (Some((u, v)), Some(forests)) => forests.get(u, v),
+                (Some(_), None) => ((value_noise(noise, x, y, 48.0, 8) - 0.5) * 4.0).max(0.0).min(1.0),
+                (None, _) => 0.0,
+            };
+            let half = self.tree_size / 2.0;
+            if hash(noise, i, j, 9) < density && !is_mountain(x, y) &&
+               [(x, y), (x - half, y), (x + half, y)].iter().all(|&(u, v)| is_land(u, v) && is_clear(u, v)) {
+                glyphs.push(Glyph::Tree(x, y, self.tree_size * (0.8 + 0.4 * hash(noise, i, j, 10))));
+            }
+        }
+
+        glyphs
+    }
+
+    /// Draws a peak standing on `(x, y)`, hatched on the side facing away from the light.
+    fn draw_mountain<N>(&self, img: &mut RgbImage, paper: &RgbImage, noise: &N, x: f64, y: f64, size: f64)
+        where N: Noise2d
+    {
+        let half = size / 2.0;
+        let peak = (x + (hash(noise, x as i64, y as i64, 11) - 0.5) * 0.2 * size, y - 0.65 * size);
+        let (left, right) = ((x - half, y), (x + half, y));
+        self.fill(img, paper, &[left, peak, right], 0.0);
+
+        let mut lines = vec![vec![left, peak, right]];
+        let hatches = (size / 4.0).round().max(1.0) as u32;
+        for k in 0..hatches {
+            let t = 0.2 + 0.6 * f64::from(k) / f64::from(hatches);
+            let start = (peak.0 + t * (right.0 - peak.0), peak.1 + t * (right.1 - peak.1));
+            lines.push(vec![start, (start.0 - 0.12 * size, start.1 + 0.6 * (y - start.1))]);
+        }
+        self.stroke(img, &lines, 1.0, self.ink);
+    }
+
+    /// Draws a round tree standing on `(x, y)`.
+    fn draw_tree(&self, img: &mut RgbImage, paper: &RgbImage, x: f64, y: f64, size: f64) {
+        self.stroke(img, &[vec![(x, y), (x, y - 0.35 * size)]], 0.8, self.ink);
+
+        let (cx, cy, radius) = (x, y - 0.75 * size, 0.4 * size);
+        let crown: Vec<_> = (0..13)
+            .map(|k| {
+                let angle = f64::from(k) * 2.0 * PI / 12.0;
+                (cx + radius * angle.cos(), cy + radius * angle.sin())
+            })
+            .collect();
+        self.fill(img, paper, &crown, 0.2);
+        self.stroke(img, &[crown], 0.8, self.ink);
+    }
+
+    /// Draws a compass rose with eight points in the top right corner, `N` above the north.
+    fn draw_compass(&self, img: &mut RgbImage, paper: &RgbImage) {
+        let (width, height) = img.dimensions();
+        let radius = self.compass_size * f64::from(width.min(height)) / 2.0;
+        let (cx, cy) = (f64::from(width) - 1.7 * radius, 1.7 * radius);
+
+        let ring: Vec<_> = (0..49)
+            .map(|k| {
+                let angle = f64::from(k) * 2.0 * PI / 48.0;
+                (cx + 0.6 * radius * angle.cos(), cy + 0.6 * radius * angle.sin())
+            })
+            .collect();
+        self.stroke(img, &[ring], 1.0, self.ink);
+
+        // Intercardinal points first, under the cardinal ones
+        for &k in &[1, 3, 5, 7, 0, 2, 4, 6] {
+            let angle = f64::from(k) * PI / 4.0;
+            let (length, side) = if k % 2 == 0 { (radius, 0.15 * radius) } else { (0.6 * radius, 0.1 * radius) };
+            let tip = (cx + length * angle.sin(), cy - length * angle.cos());
+            let left = (cx - side * angle.cos(), cy - side * angle.sin());
+            let right = (cx + side * angle.cos(), cy + side * angle.sin());
+
+            self.fill(img, paper, &[(cx, cy), tip, left], 1.0);
+            self.fill(img, paper, &[(cx, cy), tip, right], 0.0);
+            self.stroke(img, &[vec![left, tip, right, (cx, cy), left], vec![(cx, cy), tip]], 0.8, self.ink);
+        }
+
+        let (letter, top) = (0.25 * radius, cy - 1.15 * radius);
+        let (l, r) = (cx - 0.35 * letter, cx + 0.35 * letter);
+        self.stroke(img, &[vec![(l, top), (l, top - letter), (r, top), (r, top - letter)]], 1.2, self.ink);
+    }
+
+    /// Paints the pixels inside a polygon with the paper, tinted by `tint` of ink.
+    fn fill(&self, img: &mut RgbImage, paper: &RgbImage, polygon: &[(f64, f64)], tint: f64) {
+        let (width, height) = img.dimensions();
+        let (x0, y0, x1, y1) = bounds(polygon, 0.0, width, height);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                if contains(polygon, f64::from(x), f64::from(y)) {
+                    let p = paper.get_pixel(x, y);
+                    img.put_pixel(x, y, *lerp(Color::new([p.data[0], p.data[1], p.data[2]]), self.ink, tint));
+                }
+            }
+        }
+    }
+
+    /// Blends polylines `size` pixels wide over the image, within their bounds only.
+    fn stroke(&self, img: &mut RgbImage, lines: &[Vec<(f64, f64)>], size: f64, color: Color) {
+        let (width, height) = img.dimensions();
+        let points: Vec<_> = lines.iter().flat_map(|line| line.iter().cloned()).collect();
+        let (x0, y0, x1, y1) = bounds(&points, size, width, height);
+        if x1 <= x0 || y1 <= y0 {
+            return;
+        }
+
+        let mut coverage = blank(x1 - x0, y1 - y0);
+        for line in lines {
+            let shifted: Vec<_> = line.iter().map(|&(x, y)| (x - f64::from(x0), y - f64::from(y0))).collect();
+            trace_line(&mut coverage, &shifted, false, size);
+        }
+        for (u, v) in (0..y1 - y0).flat_map(|v| (0..x1 - x0).map(move |u| (u, v))) {
+            let t = coverage.get(u, v);
+            if t > 0.0 {
+                let p = *img.get_pixel(x0 + u, y0 + v);
+                img.put_pixel(x0 + u, y0 + v, *lerp(Color::new([p.data[0], p.data[1], p.data[2]]), color, t));
+            }
+        }
+    }
+}
+
+fn blank(width: u32, height: u32) -> Heightmap {
+    heightmap_from_vec(width, height, vec![0.0; width as usize * height as usize])
+}
+
+/// Pixels around the points, `margin` pixels wider, as `(x0, y0, x1, y1)` with the ends excluded.
+fn bounds(points: &[(f64, f64)], margin: f64, width: u32, height: u32) -> (u32, u32, u32, u32) {
+    let fold = |f: fn(f64, f64) -> f64, start: f64, coordinate: fn(&(f64, f64)) -> f64| {
+        points.iter().map(coordinate).fold(start, f)
+    };
+    let (x0, x1) = (fold(f64::min, ::std::f64::INFINITY, |p| p.0), fold(f64::max, ::std::f64::NEG_INFINITY, |p| p.0));
+    let (y0, y1) = (fold(f64::min, ::std::f64::INFINITY, |p| p.1), fold(f64::max, ::std::f64::NEG_INFINITY, |p| p.1));
+    let clamp = |v: f64, max: u32| v.max(0.0).min(f64::from(max)) as u32;
+
+    (clamp((x0 - margin - 1.0).floor(), width),
+     clamp((y0 - margin - 1.0).floor(), height),
+     clamp((x1 + margin + 2.0).ceil(), width),
+     clamp((y1 + margin + 2.0).ceil(), height))
+}
+
+/// Whether a point is inside a polygon, by the even-odd rule.
+fn contains(polygon: &[(f64, f64)], x: f64, y: f64) -> bool {
+    let mut inside = false;
+    let mut previous = polygon[polygon.len() - 1];
+    for &point in polygon {
+        let ((x0, y0), (x1, y1)) = (previous, point);
+        if (y0 > y) != (y1 > y) && x < x0 + (y - y0) * (x1 - x0) / (y1 - y0) {
+            inside = !inside;
+        }
+        previous = point;
+    }
+
+    inside
+}
+
+/// Rounds the corners of a polyline by cutting them twice, keeping its ends.
+fn smooth(points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut points = points.to_vec();
+    for _ in 0..2 {
+        if points.len() < 3 {
+            break;
+        }
+        let mut cut = vec![points[0]];
+        for s in points.windows(2) {
+            let ((x0, y0), (x1, y1)) = (s[0], s[1]);
+            cut.push((0.75 * x0 + 0.25 * x1, 0.75 * y0 + 0.25 * y1));
+            cut.push((0.25 * x0 + 0.75 * x1, 0.25 * y0 + 0.75 * y1));
+        }
+        cut.push(points[points.len() - 1]);
+        points = cut;
+    }
+
+    points
+}
+
+/// Points of a grid of `dx` by `dy` cells, each moved at random within its cell, odd rows being
+/// shifted by half a cell, with the column and the row of their cell.
+fn jittered<N>(noise: &N, width: f64, height: f64, dx: f64, dy: f64, salt: u64) -> Vec<(f64, f64, i64, i64)>
+    where N: Noise2d
+{
+    if dx <= 0.0 || dy <= 0.0 {
+        return Vec::new();
+    }
+
+    let (columns, rows) = ((width / dx).ceil() as i64 + 1, (height / dy).ceil() as i64 + 1);
+    (0..rows)
+        .flat_map(|j| (0..columns).map(move |i| (i, j)))
+        .map(|(i, j)| {
+            let shift = if j % 2 == 1 { 0.5 } else { 0.0 };
+            let x = (i as f64 + shift + 0.6 * (hash(noise, i, j, salt) - 0.5)) * dx;
+            let y = (j as f64 + 0.6 * (hash(noise, i, j, salt + 100) - 0.5)) * dy;
+            (x, y, i, j)
+        })
+        .collect()
+}
+
+/// Value of `noise` at a node of its lattice, the same for the same arguments.
+fn hash<N>(noise: &N, x: i64, y: i64, salt: u64) -> f64
+    where N: Noise2d
+{
+    lattice(noise, x as f64, y as f64, salt)
+}
+
+/// `noise` stretched so that its nodes are `cell` pixels apart.
+fn value_noise<N>(noise: &N, x: f64, y: f64, cell: f64, salt: u64) -> f64
+    where N: Noise2d
+{
+    lattice(noise, x / cell, y / cell, salt)
+}
+
+/// `noise` at `(u, v)` in units of its lattice, which repeats every `256` nodes. Each salt reads
+/// another part of the lattice, and negative positions wrap around.
+fn lattice<N>(noise: &N, u: f64, v: f64, salt: u64) -> f64
+    where N: Noise2d
+{
+    let wrap = |t: f64| t - 256.0 * (t / 256.0).floor();
+    noise.at(wrap(u + 37.0 * salt as f64), wrap(v + 61.0 * salt as f64))
+}
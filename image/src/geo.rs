@@ -0,0 +1,212 @@
+//! Write a `Heightmap` as a digital elevation model for GIS tools.
+use heightmap::{Heightmap, Sample};
+use std::io;
+use std::io::Write;
+
+/// Position of the map in the world.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct GeoReference {
+    /// Size of a cell in map units.
+    #[serde(default = "default_cell_size")]
+    pub cell_size: f64,
+    /// Position of the lower left corner of the map.
+    #[serde(default)]
+    pub origin_x: f64,
+    #[serde(default)]
+    pub origin_y: f64,
+    /// An elevation is `height * vertical_scale + vertical_offset`.
+    #[serde(default = "default_vertical_scale")]
+    pub vertical_scale: f64,
+    #[serde(default)]
+    pub vertical_offset: f64,
+    /// Coordinate reference system, written in GeoTIFF files only.
+    #[serde(default)]
+    pub epsg: Option<u16>,
+}
+
+fn default_cell_size() -> f64 {
+    1.0
+}
+
+fn default_vertical_scale() -> f64 {
+    1.0
+}
+
+impl Default for GeoReference {
+    fn default() -> GeoReference {
+        GeoReference {
+            cell_size: default_cell_size(),
+            origin_x: 0.0,
+            origin_y: 0.0,
+            vertical_scale: default_vertical_scale(),
+            vertical_offset: 0.0,
+            epsg: None,
+        }
+    }
+}
+
+impl GeoReference {
+    pub fn elevation(&self, height: f64) -> f64 {
+        height * self.vertical_scale + self.vertical_offset
+    }
+
+    /// World position of the center of the sample at column `x` and row `y` of a map `rows` samples
+    /// high, fractional positions being between samples.
+    pub fn position(&self, x: f64, y: f64, rows: u32) -> (f64, f64) {
+        (self.origin_x + (x + 0.5) * self.cell_size, self.origin_y + (f64::from(rows) - y - 0.5) * self.cell_size)
+    }
+}
+
+pub trait GeoExport {
+    /// ESRI ASCII grid (`.asc`).
+    fn write_asc<W: Write>(&self, w: W, georef: &GeoReference) -> io::Result<()>;
+
+    /// Single band, uncompressed, 32-bit float GeoTIFF.
+    fn write_geotiff<W: Write>(&self, w: W, georef: &GeoReference) -> io::Result<()>;
+}
+
+// TIFF field types
+const SHORT: u16 = 3;
+const LONG: u16 = 4;
+const DOUBLE: u16 = 12;
+
+enum Field {
+    Short(Vec<u16>),
+    Long(Vec<u32>),
+    Double(Vec<f64>),
+}
+
+impl Field {
+    fn kind(&self) -> u16 {
+        match *self {
+            Field::Short(_) => SHORT,
+            Field::Long(_) => LONG,
+            Field::Double(_) => DOUBLE,
+        }
+    }
+
+    fn count(&self) -> u32 {
+        match *self {
+            Field::Short(ref v) => v.len() as u32,
+            Field::Long(ref v) => v.len() as u32,
+            Field::Double(ref v) => v.len() as u32,
+        }
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        match *self {
+            Field::Short(ref v) => v.iter().flat_map(|x| x.to_le_bytes().to_vec()).collect(),
+            Field::Long(ref v) => v.iter().flat_map(|x| x.to_le_bytes().to_vec()).collect(),
+            Field::Double(ref v) => v.iter().flat_map(|x| x.to_le_bytes().to_vec()).collect(),
+        }
+    }
+}
+
+impl<T> GeoExport for Heightmap<T>
+    where T: Sample
+{
+    fn write_asc<W: Write>(&self, mut w: W, georef: &GeoReference) -> io::Result<()> {
+        writeln!(w, "ncols {}", self.width())?;
+        writeln!(w, "nrows {}", self.height())?;
+        writeln!(w, "xllcorner {}", georef.origin_x)?;
+        writeln!(w, "yllcorner {}", georef.origin_y)?;
+        writeln!(w, "cellsize {}", georef.cell_size)?;
+        writeln!(w, "NODATA_value -9999")?;
+
+        for y in 0..self.height() {
+            let row = (0..self.width())
+                .map(|x| georef.elevation(self.get(x, y)).to_string())
+                .collect::<Vec<_>>();
+            writeln!(w, "{}", row.join(" "))?;
+        }
+
+        w.flush()
+    }
+
+    fn write_geotiff<W: Write>(&self, mut w: W, georef: &GeoReference) -> io::Result<()> {
+        let data_len = u64::from(self.width()) * u64::from(self.height()) * 4;
+        if data_len > u64::from(u32::MAX) - 4096 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Map too large for a TIFF file"));
+        }
+
+        // GeoKey directory: header then (key, location, count, value) entries
+        let mut geokeys = vec![1, 1, 0, 0];
+        let mut add_key = |key: u16, value: u16| geokeys.extend_from_slice(&[key, 0, 1, value]);
+        match georef.epsg {
+            Some(code) if (4000..5000).contains(&code) => {
+                add_key(1024, 2); // GTModelTypeGeoKey: geographic
+                add_key(1025, 1); // GTRasterTypeGeoKey: pixel is area
+                add_key(2048, code); // GeographicTypeGeoKey
+            }
+            Some(code) => {
+                add_key(1024, 1); // GTModelTypeGeoKey: projected
+                add_key(1025, 1); // GTRasterTypeGeoKey: pixel is area
+                add_key(3072, code); // ProjectedCSTypeGeoKey
+            }
+            None => {
+                add_key(1024, 1); // GTModelTypeGeoKey: projected
+                add_key(1025, 1); // GTRasterTypeGeoKey: pixel is area
+            }
+        }
+        geokeys[3] = (geokeys.len() as u16 - 4) / 4;
+
+        let top = georef.origin_y + f64::from(self.height()) * georef.cell_size;
+        let mut fields: Vec<(u16, Field)> = vec![
+            (256, Field::Long(vec![self.width()])), // ImageWidth
+            (257, Field::Long(vec![self.height()])), // ImageLength
+            (258, Field::Short(vec![32])), // BitsPerSample
+            (259, Field::Short(vec![1])), // Compression: none
+            (262, Field::Short(vec![1])), // PhotometricInterpretation: black is zero
+            (273, Field::Long(vec![0])), // StripOffsets, set below
+            (277, Field::Short(vec![1])), // SamplesPerPixel
+            (278, Field::Long(vec![self.height()])), // RowsPerStrip
+            (279, Field::Long(vec![data_len as u32])), // StripByteCounts
+            (284, Field::Short(vec![1])), // PlanarConfiguration: chunky
+            (339, Field::Short(vec![3])), // SampleFormat: IEEE float
+            (33550, Field::Double(vec![georef.cell_size, georef.cell_size, 0.0])), // ModelPixelScaleTag
+            (33922, Field::Double(vec![0.0, 0.0, 0.0, georef.origin_x, top, 0.0])), // ModelTiepointTag
+            (34735, Field::Short(geokeys)), // GeoKeyDirectoryTag
+        ];
+
+        // Layout: header, directory, values too large for the directory, image data
+        let ifd_len = 2 + 12 * fields.len() as u32 + 4;
+        let extra_len: u32 = fields
+            .iter()
+            .map(|(_, f)| f.bytes().len() as u32)
+            .filter(|&l| l > 4)
+            .map(|l| (l + 1) & !1)
+            .sum();
+        fields[5].1 = Field::Long(vec![8 + ifd_len + extra_len]);
+
+        w.write_all(b"II")?;
+        w.write_all(&42u16.to_le_bytes())?;
+        w.write_all(&8u32.to_le_bytes())?;
+
+        w.write_all(&(fields.len() as u16).to_le_bytes())?;
+        let mut extra = Vec::new();
+        for (tag, field) in &fields {
+            let mut bytes = field.bytes();
+            w.write_all(&tag.to_le_bytes())?;
+            w.write_all(&field.kind().to_le_bytes())?;
+            w.write_all(&field.count().to_le_bytes())?;
+            if bytes.len() > 4 {
+                w.write_all(&(8 + ifd_len + extra.len() as u32).to_le_bytes())?;
+                if bytes.len() % 2 == 1 {
+                    bytes.push(0);
+                }
+                extra.extend_from_slice(&bytes);
+            } else {
+                bytes.resize(4, 0);
+                w.write_all(&bytes)?;
+            }
+        }
+        w.write_all(&0u32.to_le_bytes())?;
+        w.write_all(&extra)?;
+
+        for h in self.heights() {
+            w.write_all(&(georef.elevation(h.to_f64()) as f32).to_le_bytes())?;
+        }
+
+        w.flush()
+    }
+}
@@ -0,0 +1,122 @@
+//! Read heightmaps written by `Export` or by other tools.
+use export::Endianness;
+use heightmap::{Heightmap, Sample, heightmap_from_vec};
+use png;
+use png::HasParameters;
+use std::io;
+use std::io::Read;
+
+/// Reads an 8 or 16-bit grayscale PNG, values are mapped to `[0, 1]`.
+pub fn read_png<R: Read>(r: R) -> io::Result<Heightmap> {
+    // Without transformation 16-bit samples are kept as is
+    let mut decoder = png::Decoder::new(r);
+    decoder.set(png::TRANSFORM_IDENTITY);
+
+    let (info, mut reader) = decoder.read_info()?;
+    let mut buffer = vec![0; info.buffer_size()];
+    reader.next_frame(&mut buffer)?;
+
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "Height maps must be grayscale images")),
+    };
+
+    let data = match info.bit_depth {
+        png::BitDepth::Sixteen => {
+            buffer
+                .chunks(2 * channels)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]).to_f64())
+                .collect()
+        }
+        png::BitDepth::Eight => {
+            buffer
+                .chunks(channels)
+                .map(|c| f64::from(c[0]) / f64::from(u8::MAX))
+                .collect()
+        }
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "Height maps must have 8 or 16-bit samples")),
+    };
+
+    Ok(heightmap_from_vec(info.width, info.height, data))
+}
+
+/// Reads headerless 16-bit samples, values are mapped to `[0, 1]`.
+pub fn read_r16<R: Read>(r: R, width: u32, height: u32, endianness: Endianness) -> io::Result<Heightmap> {
+    let buffer = read_exact(r, width, height, 2)?;
+    let data = buffer
+        .chunks(2)
+        .map(|c| match endianness {
+                 Endianness::Little => u16::from_le_bytes([c[0], c[1]]),
+                 Endianness::Big => u16::from_be_bytes([c[0], c[1]]),
+             }
+             .to_f64())
+        .collect();
+
+    Ok(heightmap_from_vec(width, height, data))
+}
+
+/// Reads headerless little-endian 32-bit floats, values are kept as is.
+pub fn read_f32<R: Read>(r: R, width: u32, height: u32) -> io::Result<Heightmap> {
+    let buffer = read_exact(r, width, height, 4)?;
+    let data = buffer
+        .chunks(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]).to_f64())
+        .collect();
+
+    Ok(heightmap_from_vec(width, height, data))
+}
+
+fn read_exact<R: Read>(mut r: R, width: u32, height: u32, sample_size: usize) -> io::Result<Vec<u8>> {
+    let mut buffer = vec![0; width as usize * height as usize * sample_size];
+    r.read_exact(&mut buffer)?;
+
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use export::Export;
+    use heightmap::heightmap_from_vec;
+
+    #[test]
+    fn r16_byte_order() {
+        let bytes = [0x00, 0xff, 0xff, 0x00];
+
+        let little = read_r16(&bytes[..], 2, 1, Endianness::Little).unwrap();
+        assert_eq!((little.get(0, 0), little.get(1, 0)), (0xff00u16.to_f64(), 0x00ffu16.to_f64()));
+
+        let big = read_r16(&bytes[..], 2, 1, Endianness::Big).unwrap();
+        assert_eq!((big.get(0, 0), big.get(1, 0)), (0x00ffu16.to_f64(), 0xff00u16.to_f64()));
+    }
+
+    #[test]
+    fn f32_keeps_raw_values() {
+        let hmap = heightmap_from_vec(3, 1, vec![-12.5, 0.0, 1024.25]);
+        let mut bytes = Vec::new();
+        hmap.write_f32(&mut bytes).unwrap();
+
+        let read = read_f32(&bytes[..], 3, 1).unwrap();
+        assert_eq!(read.heights().cloned().collect::<Vec<_>>(), vec![-12.5, 0.0, 1024.25]);
+    }
+
+    #[test]
+    fn png16_keeps_every_level() {
+        let levels = [0u16, 1, 2, 32767, 65534, 65535];
+        let hmap = heightmap_from_vec(levels.len() as u32, 1, levels.iter().map(|&l| l.to_f64()).collect());
+        let mut bytes = Vec::new();
+        hmap.write_png16(&mut bytes).unwrap();
+
+        let read = read_png(&bytes[..]).unwrap();
+        let read_levels = read.heights().map(|&h| u16::from_f64(h)).collect::<Vec<_>>();
+        assert_eq!(read_levels, levels.to_vec());
+    }
+
+    #[test]
+    fn truncated_input_is_an_error() {
+        let bytes = [0u8; 7];
+        assert!(read_r16(&bytes[..], 2, 2, Endianness::Little).is_err());
+        assert!(read_f32(&bytes[..], 2, 1).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! Isometric view of the map as columns of layered tiles, the top of the map being at the back.
+use color::{Color, lerp};
+use color_ramp::ColorRamp;
+use heightmap::{Heightmap, Sample};
+use image::{Rgb, RgbImage};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct IsometricOptions {
+    /// Width of the top of a cell, in pixels, twice its height.
+    #[serde(default = "default_tile_size")]
+    pub tile_size: u32,
+    /// Side of a cell, in samples, the height of a cell being the mean of its samples.
+    #[serde(default = "default_cell")]
+    pub cell: u32,
+    /// Number of layers the heights from `0` to `1` are rounded down to.
+    #[serde(default = "default_layers")]
+    pub layers: u32,
+    /// Height of a layer, in pixels.
+    #[serde(default = "default_layer_height")]
+    pub layer_height: f64,
+    /// Darkening of the sides of the columns facing the left.
+    #[serde(default = "default_left")]
+    pub left: f64,
+    /// Darkening of the sides of the columns facing the right.
+    #[serde(default = "default_right")]
+    pub right: f64,
+    /// Darkening of the edges of the tops of the columns, `0` to disable.
+    #[serde(default = "default_outline")]
+    pub outline: f64,
+    #[serde(default = "default_background")]
+    pub background: Color,
+}
+
+fn default_tile_size() -> u32 {
+    16
+}
+
+fn default_cell() -> u32 {
+    8
+}
+
+fn default_layers() -> u32 {
+    16
+}
+
+fn default_layer_height() -> f64 {
+    4.0
+}
+
+fn default_left() -> f64 {
+    0.25
+}
+
+fn default_right() -> f64 {
+    0.45
+}
+
+fn default_outline() -> f64 {
+    0.15
+}
+
+fn default_background() -> Color {
+    Color::new([32, 32, 40])
+}
+
+impl Default for IsometricOptions {
+    fn default() -> IsometricOptions {
+        IsometricOptions {
+            tile_size: default_tile_size(),
+            cell: default_cell(),
+            layers: default_layers(),
+            layer_height: default_layer_height(),
+            left: default_left(),
+            right: default_right(),
+            outline: default_outline(),
+            background: default_background(),
+        }
+    }
+}
+
+impl IsometricOptions {
+    /// Renders the map as columns from the back to the front, their tops colored by the ramp at the
+    /// height of their layer. The sea is drawn as a flat surface at `sea_level`, coloured by its depth.
+    pub fn render<T>(&self, hmap: &Heightmap<T>, sea_level: f64, ramp: &ColorRamp) -> RgbImage
+        where T: Sample
+    {
+        let cell = self.cell.max(1);
+        let layers = self.layers.max(1);
+        let (columns, rows) = ((hmap.width() + cell - 1) / cell, (hmap.height() + cell - 1) / cell);
+        let half = f64::from(self.tile_size) / 2.0;
+        let quarter = half / 2.0;
+        let raised = f64::from(layers) * self.layer_height;
+
+        let width = (f64::from(columns + rows) * half).ceil() as u32;
+        let height = (f64::from(columns + rows) * quarter + raised).ceil() as u32;
+        let mut img = RgbImage::from_pixel(width, height, *self.background);
+
+        // Land stays at least a layer above the sea
+        let sea = (sea_level * f64::from(layers)).floor().max(0.0).min(f64::from(layers));
+        for diagonal in 0..columns + rows - 1 {
+            for i in diagonal.saturating_sub(rows - 1)..(diagonal + 1).min(columns) {
+                let j = diagonal - i;
+                let mean = mean(hmap, i * cell, j * cell, cell);
+                let (level, top) = if mean < sea_level {
+                    (sea, ramp.get(mean))
+                } else {
+                    let level = (mean * f64::from(layers)).floor().max(sea + 1.0).min(f64::from(layers));
+                    (level, ramp.get(level / f64::from(layers)))
+                };
+
+                let x = (f64::from(i) - f64::from(j) + f64::from(rows)) * half;
+                let base = f64::from(diagonal) * quarter + quarter + raised;
+                self.draw_column(&mut img, (x, base), level * self.layer_height, top);
+            }
+        }
+
+        img
+    }
+
+    /// Draws a column whose top is `rise` pixels above the center of its base.
+    fn draw_column(&self, img: &mut RgbImage, (x, base): (f64, f64), rise: f64, top: Color) {
+        let (width, height) = img.dimensions();
+        let half = f64::from(self.tile_size) / 2.0;
+        let quarter = half / 2.0;
+        let center = base - rise;
+        let shade = |t: f64| *lerp(top, Color::new([0, 0, 0]), t);
+        let edge = shade(self.outline);
+
+        let (u0, u1) = ((x - half).floor().max(0.0) as u32, ((x + half).ceil() as u32).min(width));
+        for u in u0..u1 {
+            let dx = f64::from(u) + 0.5 - x;
+            if dx.abs() > half {
+                continue;
+            }
+            // Half the height of the top at this column of pixels
+            let extent = quarter * (1.0 - dx.abs() / half);
+            let side: Rgb<u8> = shade(if dx < 0.0 { self.left } else { self.right });
+
+            let (v0, v1) = ((center - extent).round().max(0.0) as u32, ((base + extent).round() as u32).min(height));
+            for v in v0..v1 {
+                let dy = f64::from(v) + 0.5 - center;
+                let color = if dy >= extent {
+                    side
+                } else if self.outline > 0.0 && extent - dy.abs() < 1.0 {
+                    edge
+                } else {
+                    *top
+                };
+                img.put_pixel(u, v, color);
+            }
+        }
+    }
+}
+
+/// Mean height of the samples of the cell `cell` samples wide whose top left sample is `(x, y)`.
+fn mean<T>(hmap: &Heightmap<T>, x: u32, y: u32, cell: u32) -> f64
+    where T: Sample
+{
+    let (x1, y1) = ((x + cell).min(hmap.width()), (y + cell).min(hmap.height()));
+    let sum: f64 = (y..y1).flat_map(|v| (x..x1).map(move |u| (u, v))).map(|(u, v)| hmap.get(u, v)).sum();
+    sum / f64::from((x1 - x) * (y1 - y))
+}
@@ -1,17 +1,41 @@
 extern crate heightmap;
 extern crate image;
 extern crate noise2d;
+extern crate png;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 
 mod color;
+mod color_map;
 mod color_ramp;
+mod export;
+mod fantasy;
+mod geo;
+mod import;
+mod isometric;
+mod line;
+mod normal_map;
+mod occlusion;
+mod overlay;
+mod preview;
 mod shade;
 mod to_image;
 
+pub use image::{GrayImage, Luma, Rgb, RgbImage};
 pub use to_image::ToImage;
 pub use color::Color;
+pub use color_map::ColorMap;
 pub use color_ramp::ColorRamp;
-pub use shade::Shadable;
+pub use export::{Endianness, Export, png_bytes};
+pub use fantasy::FantasyOptions;
+pub use geo::{GeoExport, GeoReference};
+pub use import::{read_f32, read_png, read_r16};
+pub use isometric::IsometricOptions;
+pub use line::{draw_line, trace_line};
+pub use normal_map::{Kernel, NormalMap, NormalMapOptions, Space};
+pub use overlay::overlay;
+pub use preview::PreviewOptions;
+pub use occlusion::{ambient_occlusion, cast_shadows};
+pub use shade::{Bathymetry, Light, Normal, Shadable, ShadeOptions};
 pub use shade::Vec3;
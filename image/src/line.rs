@@ -0,0 +1,46 @@
+//! Anti-aliased polylines.
+use color::Color;
+use heightmap::{Heightmap, heightmap_from_vec};
+use image::RgbImage;
+use overlay::overlay;
+
+/// Draws a polyline `size` pixels wide, its edges blended over a pixel.
+pub fn draw_line(img: &mut RgbImage, points: &[(f64, f64)], closed: bool, size: f64, color: Color) {
+    let (width, height) = img.dimensions();
+    let mut coverage = heightmap_from_vec(width, height, vec![0.0; width as usize * height as usize]);
+    trace_line(&mut coverage, points, closed, size);
+
+    overlay(img, &coverage, color, 1.0);
+}
+
+/// Keeps the highest share of each pixel covered by the segments of a polyline, pixels being one
+/// wide around their center.
+pub fn trace_line(coverage: &mut Heightmap, points: &[(f64, f64)], closed: bool, size: f64) {
+    let (width, height) = (coverage.width(), coverage.height());
+    let radius = size / 2.0;
+    let closing = if closed && points.len() > 2 { Some((points[points.len() - 1], points[0])) } else { None };
+    let segments = points.windows(2).map(|s| (s[0], s[1])).chain(closing);
+
+    for ((x0, y0), (x1, y1)) in segments {
+        let (u0, u1) = ((x0.min(x1) - radius - 1.0).floor().max(0.0), (x0.max(x1) + radius + 1.0).ceil());
+        let (v0, v1) = ((y0.min(y1) - radius - 1.0).floor().max(0.0), (y0.max(y1) + radius + 1.0).ceil());
+        if u1 < 0.0 || v1 < 0.0 {
+            continue;
+        }
+        let (dx, dy) = (x1 - x0, y1 - y0);
+        let length = dx * dx + dy * dy;
+
+        for v in v0 as u32..(v1 as u32 + 1).min(height) {
+            for u in u0 as u32..(u1 as u32 + 1).min(width) {
+                let (px, py) = (f64::from(u), f64::from(v));
+                let t = if length > 0.0 { (((px - x0) * dx + (py - y0) * dy) / length).max(0.0).min(1.0) } else { 0.0 };
+                let distance = (px - x0 - t * dx).hypot(py - y0 - t * dy);
+
+                let share = (radius + 0.5 - distance).max(0.0).min(1.0);
+                if share > coverage.get(u, v) {
+                    coverage.set(u, v, share);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Normal maps, for shaders lighting a flat or low resolution surface.
+use heightmap::{Heightmap, Sample};
+use image::{ImageBuffer, Rgb};
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::Error;
+use shade::{Normal, Vec3};
+
+/// Estimation of the slope at a sample.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kernel {
+    /// Difference of the two neighbours.
+    Central,
+    /// Difference of the two neighbouring columns or rows, smoother.
+    Sobel,
+}
+
+/// Base in which normals are written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Space {
+    /// `x` to the right and `y` to the top of the image, `z` out of the surface.
+    Tangent,
+    /// Coordinates of the exported meshes: `x` to the right, `y` up and `z` to the bottom of the image.
+    Object,
+}
+
+impl Default for Kernel {
+    fn default() -> Kernel {
+        Kernel::Sobel
+    }
+}
+
+impl Default for Space {
+    fn default() -> Space {
+        Space::Tangent
+    }
+}
+
+impl Serialize for Kernel {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_str(match *self {
+                                     Kernel::Central => "central",
+                                     Kernel::Sobel => "sobel",
+                                 })
+    }
+}
+
+impl Deserialize for Kernel {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer
+    {
+        let string = String::deserialize(deserializer)?;
+        match string.as_str() {
+            "central" => Ok(Kernel::Central),
+            "sobel" => Ok(Kernel::Sobel),
+            s => Err(D::Error::unknown_variant(s, &["central", "sobel"])),
+        }
+    }
+}
+
+impl Serialize for Space {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_str(match *self {
+                                     Space::Tangent => "tangent",
+                                     Space::Object => "object",
+                                 })
+    }
+}
+
+impl Deserialize for Space {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer
+    {
+        let string = String::deserialize(deserializer)?;
+        match string.as_str() {
+            "tangent" => Ok(Space::Tangent),
+            "object" => Ok(Space::Object),
+            s => Err(D::Error::unknown_variant(s, &["tangent", "object"])),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct NormalMapOptions {
+    /// Height of a sample at `1.0`, in distances between two samples.
+    #[serde(default = "default_strength")]
+    pub strength: f64,
+    #[serde(default)]
+    pub kernel: Kernel,
+    #[serde(default)]
+    pub space: Space,
+}
+
+fn default_strength() -> f64 {
+    100.0
+}
+
+impl Default for NormalMapOptions {
+    fn default() -> NormalMapOptions {
+        NormalMapOptions {
+            strength: default_strength(),
+            kernel: Kernel::default(),
+            space: Space::default(),
+        }
+    }
+}
+
+pub trait NormalMap {
+    /// Normals encoded as colours, each component being mapped from `[-1, 1]` to `[0, 255]`.
+    fn normal_map(&self, options: &NormalMapOptions) -> ImageBuffer<Rgb<u8>, Vec<u8>>;
+}
+
+impl<T> NormalMap for Heightmap<T>
+    where T: Sample
+{
+    fn normal_map(&self, options: &NormalMapOptions) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        let encode = |v: f64| ((v + 1.0) * 127.5).round() as u8;
+
+        ImageBuffer::from_fn(self.width(), self.height(), |x, y| {
+            let n = match options.kernel {
+                Kernel::Central => self.scaled_normal(x, y, options.strength),
+                Kernel::Sobel => {
+                    let (dx, dy) = self.gradient(x, y, options.strength);
+                    Vec3::surface(dx, dy)
+                }
+            };
+
+            // Normals have `y` towards the bottom of the map
+            let normal = match options.space {
+                Space::Tangent => [n.x(), -n.y(), n.z()],
+                Space::Object => [n.x(), n.z(), n.y()],
+            };
+
+            Rgb([encode(normal[0]), encode(normal[1]), encode(normal[2])])
+        })
+    }
+}
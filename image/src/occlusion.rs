@@ -0,0 +1,84 @@
+//! Light hidden by the surrounding terrain.
+//!
+//! `scale` is the height of a sample at `1.0`, in distances between two samples.
+use heightmap::{Heightmap, Sample, heightmap_from_vec};
+use std::f64::consts::PI;
+
+/// Directions searched for the horizon of a sample.
+const DIRECTIONS: u32 = 8;
+
+/// `1.0` for the samples in the shadow of the terrain for a light coming from `azimuth`, in degrees
+/// clockwise from the top of the map, and `altitude` degrees above the horizon, `0.0` elsewhere.
+pub fn cast_shadows<T>(hmap: &Heightmap<T>, azimuth: f64, altitude: f64, scale: f64) -> Heightmap
+    where T: Sample
+{
+    let (width, height) = (hmap.width(), hmap.height());
+    let top = hmap.heights().fold(::std::f64::NEG_INFINITY, |m, h| m.max(h.to_f64())) * scale;
+    let (dx, dy) = (azimuth.to_radians().sin(), -azimuth.to_radians().cos());
+    let rise = altitude.to_radians().tan();
+
+    let shadows = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let start = hmap.get(x, y) * scale;
+            let mut step = 1.0;
+            loop {
+                // Walks towards the light until the ray is above the highest sample
+                let ray = start + step * rise;
+                let (u, v) = ((f64::from(x) + dx * step).round(), (f64::from(y) + dy * step).round());
+                if ray > top || u < 0.0 || v < 0.0 || u >= f64::from(width) || v >= f64::from(height) {
+                    return 0.0;
+                }
+                if hmap.get(u as u32, v as u32) * scale > ray {
+                    return 1.0;
+                }
+                step += 1.0;
+            }
+        })
+        .collect();
+
+    heightmap_from_vec(width, height, shadows)
+}
+
+/// Share of the sky hidden from each sample by the terrain within `radius` samples, from `0.0` in the
+/// open to `1.0` at the bottom of a well (horizon based).
+pub fn ambient_occlusion<T>(hmap: &Heightmap<T>, radius: u32, scale: f64) -> Heightmap
+    where T: Sample
+{
+    let (width, height) = (hmap.width(), hmap.height());
+    let directions: Vec<(f64, f64)> = (0..DIRECTIONS)
+        .map(|i| {
+            let angle = f64::from(i) * 2.0 * PI / f64::from(DIRECTIONS);
+            (angle.cos(), angle.sin())
+        })
+        .collect();
+
+    let occlusion = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let h = hmap.get(x, y) * scale;
+            let hidden: f64 = directions
+                .iter()
+                .map(|&(dx, dy)| {
+                    // Sine of the highest elevation angle of the horizon
+                    let mut horizon = 0.0f64;
+                    for step in 1..radius + 1 {
+                        let step = f64::from(step);
+                        let (u, v) = ((f64::from(x) + dx * step).round(), (f64::from(y) + dy * step).round());
+                        if u < 0.0 || v < 0.0 || u >= f64::from(width) || v >= f64::from(height) {
+                            break;
+                        }
+
+                        let rise = hmap.get(u as u32, v as u32) * scale - h;
+                        horizon = horizon.max(rise / rise.hypot(step));
+                    }
+                    horizon
+                })
+                .sum();
+
+            hidden / f64::from(DIRECTIONS)
+        })
+        .collect();
+
+    heightmap_from_vec(width, height, occlusion)
+}
@@ -0,0 +1,16 @@
+//! Colour blended over a rendered map.
+use color::{Color, lerp};
+use heightmap::{Heightmap, Sample};
+use image::RgbImage;
+
+/// Blends `color` over each pixel by `opacity` times the value of the mask, from `0` to `1`.
+pub fn overlay<T>(img: &mut RgbImage, mask: &Heightmap<T>, color: Color, opacity: f64)
+    where T: Sample
+{
+    for (x, y, p) in img.enumerate_pixels_mut() {
+        let t = mask.get(x, y).max(0.0).min(1.0) * opacity;
+        if t > 0.0 {
+            *p = *lerp(Color::new([p.data[0], p.data[1], p.data[2]]), color, t);
+        }
+    }
+}
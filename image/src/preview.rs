@@ -0,0 +1,222 @@
+//! Perspective view of the map from a camera above it, ray-marched on the CPU.
+use color::{Color, lerp};
+use heightmap::{Heightmap, Sample};
+use image::RgbImage;
+use shade::Vec3;
+
+type Point = (f64, f64, f64);
+
+/// Camera of the preview. The default one looks at the center of the map from the south:
+///
+/// ```yaml
+/// preview:
+///   camera: 512 1400 600
+///   pitch: 30
+///   exaggeration: 0.15
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PreviewOptions {
+    /// Width of the image, in pixels.
+    #[serde(default = "default_width")]
+    pub width: u32,
+    /// Height of the image, in pixels.
+    #[serde(default = "default_height")]
+    pub height: u32,
+    /// Position of the camera, in samples from the top left corner of the map and in distances
+    /// between two samples above the height `0`, far enough to see the whole map when not set.
+    pub camera: Option<Vec3>,
+    /// Direction the camera looks towards, in degrees clockwise from the top of the map.
+    #[serde(default)]
+    pub yaw: f64,
+    /// Angle of the view below the horizon, in degrees.
+    #[serde(default = "default_pitch")]
+    pub pitch: f64,
+    /// Horizontal field of view, in degrees.
+    #[serde(default = "default_fov")]
+    pub fov: f64,
+    /// Height of a sample at `1.0`, as a share of the larger side of the map.
+    #[serde(default = "default_exaggeration")]
+    pub exaggeration: f64,
+    /// Colour of the sky, and of the haze over the distant terrain.
+    #[serde(default = "default_sky")]
+    pub sky: Color,
+    /// Share of the colour of the farthest terrain hidden by the haze, `0` to disable.
+    #[serde(default = "default_haze")]
+    pub haze: f64,
+}
+
+fn default_width() -> u32 {
+    800
+}
+
+fn default_height() -> u32 {
+    600
+}
+
+fn default_pitch() -> f64 {
+    35.0
+}
+
+fn default_fov() -> f64 {
+    60.0
+}
+
+fn default_exaggeration() -> f64 {
+    0.1
+}
+
+fn default_sky() -> Color {
+    Color::new([178, 204, 230])
+}
+
+fn default_haze() -> f64 {
+    0.4
+}
+
+impl Default for PreviewOptions {
+    fn default() -> PreviewOptions {
+        PreviewOptions {
+            width: default_width(),
+            height: default_height(),
+            camera: None,
+            yaw: 0.0,
+            pitch: default_pitch(),
+            fov: default_fov(),
+            exaggeration: default_exaggeration(),
+            sky: default_sky(),
+            haze: default_haze(),
+        }
+    }
+}
+
+impl PreviewOptions {
+    /// Renders the map seen from the camera, each sample with its colour in `colors`, the sea being a
+    /// flat surface at `sea_level`, `0` showing the sea floor.
+    pub fn render<T>(&self, hmap: &Heightmap<T>, sea_level: f64, colors: &RgbImage) -> RgbImage
+        where T: Sample
+    {
+        let (width, height) = (f64::from(hmap.width()), f64::from(hmap.height()));
+        let scale = self.exaggeration * width.max(height);
+        let top = hmap.heights().fold(::std::f64::NEG_INFINITY, |m, h| m.max(h.to_f64())) * scale;
+
+        let (yaw, pitch) = (self.yaw.to_radians(), self.pitch.to_radians());
+        let forward = (yaw.sin() * pitch.cos(), -yaw.cos() * pitch.cos(), -pitch.sin());
+        let right = (yaw.cos(), yaw.sin(), 0.0);
+        let up = (yaw.sin() * pitch.sin(), -yaw.cos() * pitch.sin(), pitch.cos());
+        let eye = match self.camera {
+            Some(camera) => (camera.x(), camera.y(), camera.z()),
+            None => {
+                // Backs away from the center of the map, halfway up its highest sample
+                let distance = 1.6 * width.max(height);
+                ((width - 1.0) / 2.0 - forward.0 * distance,
+                 (height - 1.0) / 2.0 - forward.1 * distance,
+                 top / 2.0 - forward.2 * distance)
+            }
+        };
+
+        // Distance to the farthest corner, where the haze is the thickest
+        let far = [(0.0, 0.0), (width, 0.0), (0.0, height), (width, height)]
+            .iter()
+            .map(|&(x, y)| (x - eye.0).hypot(y - eye.1).hypot(eye.2))
+            .fold(0.0, f64::max);
+        let half = (self.fov.to_radians() / 2.0).tan();
+        let aspect = f64::from(self.height) / f64::from(self.width);
+        // Angle covered by a pixel, the steps growing with the distance as the pixels do
+        let pixel = 2.0 * half / f64::from(self.width);
+
+        RgbImage::from_fn(self.width, self.height, |i, j| {
+            let u = (2.0 * (f64::from(i) + 0.5) / f64::from(self.width) - 1.0) * half;
+            let v = (1.0 - 2.0 * (f64::from(j) + 0.5) / f64::from(self.height)) * half * aspect;
+            let direction = normalize((forward.0 + u * right.0 + v * up.0,
+                                       forward.1 + u * right.1 + v * up.1,
+                                       forward.2 + u * right.2 + v * up.2));
+
+            match march(hmap, scale, sea_level, top, eye, direction, pixel) {
+                Some((x, y, t, side)) => {
+                    let p = colors.get_pixel(x.round() as u32, y.round() as u32);
+                    let mut color = Color::new([p.data[0], p.data[1], p.data[2]]);
+                    if side {
+                        color = lerp(color, Color::new([0, 0, 0]), 0.4);
+                    }
+                    *lerp(color, self.sky, self.haze * t / far)
+                }
+                None => *self.sky,
+            }
+        })
+    }
+}
+
+fn normalize((x, y, z): Point) -> Point {
+    let length = (x * x + y * y + z * z).sqrt();
+    (x / length, y / length, z / length)
+}
+
+/// Position on the map where the ray from `eye` first meets the terrain, with the distance to it and
+/// whether it meets the sides of the map, which stand on the height `0`.
+fn march<T>(hmap: &Heightmap<T>, scale: f64, sea_level: f64, top: f64, eye: Point, direction: Point, pixel: f64)
+            -> Option<(f64, f64, f64, bool)>
+    where T: Sample
+{
+    let (width, height) = (f64::from(hmap.width() - 1), f64::from(hmap.height() - 1));
+    // Kept on the map against rounding errors on its edges
+    let at = |t: f64| {
+        ((eye.0 + t * direction.0).max(0.0).min(width),
+         (eye.1 + t * direction.1).max(0.0).min(height),
+         eye.2 + t * direction.2)
+    };
+    let above = |t: f64| {
+        let (x, y, z) = at(t);
+        z - hmap.interpolate(x, y).max(sea_level) * scale
+    };
+
+    // Part of the ray over the map and below its highest sample
+    let (mut start, mut end) = (0.0f64, ::std::f64::INFINITY);
+    for &(origin, d, max) in &[(eye.0, direction.0, width), (eye.1, direction.1, height)] {
+        if d == 0.0 {
+            if origin < 0.0 || origin > max {
+                return None;
+            }
+        } else {
+            let (a, b) = (-origin / d, (max - origin) / d);
+            start = start.max(a.min(b));
+            end = end.min(a.max(b));
+        }
+    }
+    if direction.2 < 0.0 {
+        start = start.max((top - eye.2) / direction.2);
+    } else if eye.2 > top {
+        return None;
+    } else if direction.2 > 0.0 {
+        end = end.min((top - eye.2) / direction.2);
+    }
+
+    if start > end {
+        return None;
+    }
+    if above(start) < 0.0 {
+        let (x, y, z) = at(start);
+        return if z < 0.0 { None } else { Some((x, y, start, true)) };
+    }
+
+    let (mut previous, mut t) = (start, start);
+    while t <= end {
+        if above(t) <= 0.0 {
+            // Narrows the crossing down between the last two steps
+            let (mut a, mut b) = (previous, t);
+            for _ in 0..8 {
+                let middle = (a + b) / 2.0;
+                if above(middle) <= 0.0 {
+                    b = middle;
+                } else {
+                    a = middle;
+                }
+            }
+            let (x, y, _) = at(b);
+            return Some((x, y, b, false));
+        }
+        previous = t;
+        t += (t * pixel).max(0.5);
+    }
+
+    None
+}
@@ -1,6 +1,7 @@
 use color::{Color, lerp};
-use heightmap::Heightmap;
+use heightmap::{Heightmap, Sample};
 use image::{ImageBuffer, Rgb};
+use occlusion::{ambient_occlusion, cast_shadows};
 use serde::{Serialize, Serializer, Deserialize, Deserializer};
 use serde::de::Error;
 
@@ -12,6 +13,25 @@ impl Vec3 {
         Vec3(x, y, z)
     }
 
+    pub fn x(self) -> f64 {
+        self.0
+    }
+
+    pub fn y(self) -> f64 {
+        self.1
+    }
+
+    pub fn z(self) -> f64 {
+        self.2
+    }
+
+    /// Unit normal of a surface whose slopes towards the right and the bottom of the map are `dx`
+    /// and `dy`, `z` pointing up.
+    pub fn surface(dx: f64, dy: f64) -> Vec3 {
+        let n = (dx * dx + dy * dy + 1.0).sqrt();
+        Vec3(-dx / n, -dy / n, 1.0 / n)
+    }
+
     fn dot(self, v: &Vec3) -> f64 {
         self.0 * v.0 + self.1 * v.1 + self.2 * v.2
     }
@@ -46,58 +66,264 @@ impl Deserialize for Vec3 {
     }
 }
 
-trait Normal {
-    fn normal(&self, x: u32, y: u32) -> Vec3;
+/// Normal of the surface of a map, `z` pointing up.
+pub trait Normal {
+    fn normal(&self, x: u32, y: u32) -> Vec3 {
+        self.scaled_normal(x, y, 1.0)
+    }
+
+    /// Normal once the heights are multiplied by `factor`, relative to the distance between samples.
+    fn scaled_normal(&self, x: u32, y: u32, factor: f64) -> Vec3;
 }
 
-impl Normal for Heightmap {
-    fn normal(&self, x: u32, y: u32) -> Vec3 {
-        let nx = if x == 0 {
-            (self.get(x + 1, y) - self.get(x, y)) * 2.0
-        } else if x == self.width() - 1 {
-            (self.get(x, y) - self.get(x - 1, y)) * 2.0
-        } else {
-            (self.get(x + 1, y) - self.get(x - 1, y))
-        };
+impl<T> Normal for Heightmap<T>
+    where T: Sample
+{
+    fn scaled_normal(&self, x: u32, y: u32, factor: f64) -> Vec3 {
+        let (dx, dy) = self.central_gradient(x, y, factor);
+        Vec3::surface(dx, dy)
+    }
+}
 
-        let ny = if y == 0 {
-            (self.get(x, y + 1) - self.get(x, y)) * 2.0
-        } else if y == self.height() - 1 {
-            (self.get(x, y) - self.get(x, y - 1)) * 2.0
-        } else {
-            (self.get(x, y + 1) - self.get(x, y - 1))
-        };
+/// Light of a multi-directional hillshade.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Light {
+    /// Direction the light comes from, in degrees clockwise from the top of the map.
+    pub azimuth: f64,
+    /// Share of the shading, relative to the other lights.
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+/// Shading of the sea floor, fading with the depth.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Bathymetry {
+    /// Intensity of the shading at the sea level.
+    #[serde(default = "default_water_intensity")]
+    pub intensity: f64,
+    /// Depth below the sea level from which the sea floor is no longer shaded.
+    #[serde(default = "default_fade")]
+    pub fade: f64,
+}
+
+fn default_water_intensity() -> f64 {
+    0.5
+}
+
+fn default_fade() -> f64 {
+    0.25
+}
+
+impl Default for Bathymetry {
+    fn default() -> Bathymetry {
+        Bathymetry {
+            intensity: default_water_intensity(),
+            fade: default_fade(),
+        }
+    }
+}
+
+/// Hillshade as in GIS tools, with cast shadows and ambient occlusion.
+///
+/// Several weighted lights give a multi-directional hillshade:
+///
+/// ```yaml
+/// lights:
+///   - {azimuth: 225, weight: 0.5}
+///   - {azimuth: 270}
+///   - {azimuth: 315}
+///   - {azimuth: 360, weight: 0.5}
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ShadeOptions {
+    /// Direction the light comes from, in degrees clockwise from the top of the map, when there are
+    /// no `lights`.
+    #[serde(default = "default_azimuth")]
+    pub azimuth: f64,
+    #[serde(default)]
+    pub lights: Vec<Light>,
+    /// Angle of the lights above the horizon, in degrees.
+    #[serde(default = "default_altitude")]
+    pub altitude: f64,
+    /// `0` leaves the colors as they are, `1` goes from the dark color facing away from the light to
+    /// the light color facing it.
+    #[serde(default = "default_intensity")]
+    pub intensity: f64,
+    /// Height of a sample at `1.0`, in distances between two samples.
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    /// Darkening of the samples in the shadow of the terrain, `0` to disable.
+    #[serde(default = "default_shadow")]
+    pub shadow: f64,
+    /// Darkening of the samples whose sky is fully hidden, `0` to disable.
+    #[serde(default = "default_occlusion")]
+    pub occlusion: f64,
+    /// Distance in samples searched for the horizon hiding the sky.
+    #[serde(default = "default_occlusion_radius")]
+    pub occlusion_radius: u32,
+    /// Shading of the samples under water, left as they are when not set.
+    pub bathymetry: Option<Bathymetry>,
+}
+
+fn default_azimuth() -> f64 {
+    315.0
+}
 
-        let n = (nx * nx + ny * ny + 4.0).sqrt();
+fn default_altitude() -> f64 {
+    45.0
+}
+
+fn default_intensity() -> f64 {
+    1.0
+}
+
+fn default_scale() -> f64 {
+    50.0
+}
+
+fn default_shadow() -> f64 {
+    0.5
+}
+
+fn default_occlusion() -> f64 {
+    0.5
+}
 
-        Vec3(-nx / n, -ny / n, 2.0 / n)
+fn default_occlusion_radius() -> u32 {
+    16
+}
+
+impl Default for ShadeOptions {
+    fn default() -> ShadeOptions {
+        ShadeOptions {
+            azimuth: default_azimuth(),
+            lights: Vec::new(),
+            altitude: default_altitude(),
+            intensity: default_intensity(),
+            scale: default_scale(),
+            shadow: default_shadow(),
+            occlusion: default_occlusion(),
+            occlusion_radius: default_occlusion_radius(),
+            bathymetry: None,
+        }
+    }
+}
+
+impl ShadeOptions {
+    /// Azimuth of each light, with its weight, the weights adding up to `1`.
+    pub fn lights(&self) -> Vec<(f64, f64)> {
+        let total: f64 = self.lights.iter().map(|l| l.weight).sum();
+        if total > 0.0 {
+            self.lights.iter().map(|l| (l.azimuth, l.weight / total)).collect()
+        } else {
+            vec![(self.azimuth, 1.0)]
+        }
+    }
+
+    /// Unit vector pointing to a light coming from `azimuth`, `z` pointing up.
+    pub fn direction(&self, azimuth: f64) -> Vec3 {
+        let (azimuth, altitude) = (azimuth.to_radians(), self.altitude.to_radians());
+        Vec3(altitude.cos() * azimuth.sin(), -altitude.cos() * azimuth.cos(), altitude.sin())
     }
 }
 
 pub trait Shadable {
-    fn shade(&mut self, hmap: &Heightmap, light: &Vec3, c0: Color, c1: Color);
+    /// Shades the land above `0.5` from a `light` direction, without occlusion.
+    fn shade<T: Sample>(&mut self, hmap: &Heightmap<T>, light: &Vec3, c0: Color, c1: Color);
+
+    /// Shades the land above `sea_level`, and the sea floor with a bathymetry, flat ground keeping
+    /// its color, towards `c0` where lit and `c1` where in the dark.
+    fn hillshade<T: Sample>(&mut self,
+                            hmap: &Heightmap<T>,
+                            sea_level: f64,
+                            options: &ShadeOptions,
+                            c0: Color,
+                            c1: Color);
+}
+
+/// Color of a pixel at a shade from `0` (`c1`) to `1` (`c0`), `0.5` keeping it.
+fn blend(p: &mut Rgb<u8>, d: f64, c0: Color, c1: Color) {
+    *p = if d < 0.0 {
+        *c1
+    } else if d > 1.0 {
+        *c0
+    } else if d < 0.5 {
+        *lerp(c1, Color::new(p.data), 2.0 * d)
+    } else {
+        *lerp(Color::new(p.data), c0, 2.0 * d - 1.0)
+    };
 }
 
 impl Shadable for ImageBuffer<Rgb<u8>, Vec<u8>> {
-    fn shade(&mut self, hmap: &Heightmap, light: &Vec3, c0: Color, c1: Color) {
+    fn shade<T: Sample>(&mut self, hmap: &Heightmap<T>, light: &Vec3, c0: Color, c1: Color) {
         for y in 0..self.height() {
             for x in 0..self.width() {
                 if hmap.get(x, y) > 0.5 {
                     let mut d = light.dot(&hmap.normal(x, y));
                     d = d * 35.0 + 0.5;
-                    let p = self.get_pixel_mut(x, y);
-
-                    *p = if d < 0.0 {
-                        *c1
-                    } else if d > 1.0 {
-                        *c0
-                    } else if d < 0.5 {
-                        *lerp(c1, Color::new(p.data), 2.0 * d)
-                    } else {
-                        *lerp(Color::new(p.data), c0, 2.0 * d - 1.0)
-                    };
+                    blend(self.get_pixel_mut(x, y), d, c0, c1);
                 }
             }
         }
     }
+
+    fn hillshade<T: Sample>(&mut self,
+                            hmap: &Heightmap<T>,
+                            sea_level: f64,
+                            options: &ShadeOptions,
+                            c0: Color,
+                            c1: Color) {
+        let lights: Vec<_> = options.lights()
+            .into_iter()
+            .map(|(azimuth, weight)| {
+                let shadows = if options.shadow > 0.0 && options.altitude < 90.0 {
+                    Some(cast_shadows(hmap, azimuth, options.altitude, options.scale))
+                } else {
+                    None
+                };
+                (options.direction(azimuth), weight, shadows)
+            })
+            .collect();
+        let occlusion = if options.occlusion > 0.0 {
+            Some(ambient_occlusion(hmap, options.occlusion_radius, options.scale))
+        } else {
+            None
+        };
+
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let land = hmap.get(x, y) > sea_level;
+                let intensity = match options.bathymetry {
+                    _ if land => options.intensity,
+                    Some(ref b) if b.fade > 0.0 => {
+                        b.intensity * (1.0 - (sea_level - hmap.get(x, y)) / b.fade).max(0.0)
+                    }
+                    _ => continue,
+                };
+
+                // Lambert illumination relative to the one of flat ground, darkened by the shadows on land
+                let normal = hmap.scaled_normal(x, y, options.scale);
+                let lit: f64 = lights.iter()
+                    .map(|&(ref light, weight, ref shadows)| {
+                        let mut lit = light.dot(&normal).max(0.0) / light.z().max(1e-6);
+                        if let (&Some(ref shadows), true) = (shadows, land) {
+                            lit *= 1.0 - options.shadow * shadows.get(x, y);
+                        }
+                        lit * weight
+                    })
+                    .sum();
+
+                let mut d = 0.5 + intensity * (lit - 1.0) / 2.0;
+                if let (&Some(ref occlusion), true) = (&occlusion, land) {
+                    d *= 1.0 - options.occlusion * occlusion.get(x, y);
+                }
+
+                blend(self.get_pixel_mut(x, y), d, c0, c1);
+            }
+        }
+    }
 }
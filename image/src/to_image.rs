@@ -1,4 +1,4 @@
-use heightmap::Heightmap;
+use heightmap::{Heightmap, Sample};
 use image::{ImageBuffer, Luma};
 use noise2d::Noise2d;
 
@@ -25,7 +25,9 @@ impl ToImage for dyn Noise2d {
     }
 }
 
-impl ToImage for Heightmap {
+impl<T> ToImage for Heightmap<T>
+    where T: Sample
+{
     fn to_image(&self) -> ImageBuffer<Luma<u8>, Vec<u8>> {
         ImageBuffer::from_fn(self.width(),
                              self.height(),
@@ -0,0 +1,131 @@
+//! glTF 2.0 export, textured with a render of the map.
+use image::{RgbImage, png_bytes};
+use mesh::Mesh;
+use serde_json;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+// glTF constants
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const LINEAR: u32 = 9729;
+const LINEAR_MIPMAP_LINEAR: u32 = 9987;
+const CLAMP_TO_EDGE: u32 = 33071;
+
+fn floats<'a, I: Iterator<Item = &'a f64>>(values: I) -> Vec<u8> {
+    values.flat_map(|&v| (v as f32).to_le_bytes().to_vec()).collect()
+}
+
+fn pad(bytes: &mut Vec<u8>, value: u8) {
+    while bytes.len() % 4 != 0 {
+        bytes.push(value);
+    }
+}
+
+impl Mesh {
+    /// Writes a `.glb` file, or a `.gltf` file along with its `.bin` buffer for any other extension.
+    pub fn write_gltf<P: AsRef<Path>>(&self, path: P, texture: &RgbImage) -> io::Result<()> {
+        let path = path.as_ref();
+        let binary = path.extension().map_or(false, |e| e == "glb");
+
+        // Buffer views, each aligned on 4 bytes
+        let mut buffer = Vec::new();
+        let mut views = Vec::new();
+        let mut view = |buffer: &mut Vec<u8>, data: Vec<u8>, target: Option<u32>| {
+            let offset = buffer.len();
+            buffer.extend_from_slice(&data);
+            pad(buffer, 0);
+
+            views.push(match target {
+                Some(target) => json!({"buffer": 0, "byteOffset": offset, "byteLength": data.len(), "target": target}),
+                None => json!({"buffer": 0, "byteOffset": offset, "byteLength": data.len()}),
+            });
+        };
+
+        view(&mut buffer, floats(self.positions().iter().flat_map(|p| p.iter())), Some(ARRAY_BUFFER));
+        view(&mut buffer, floats(self.normals().iter().flat_map(|n| n.iter())), Some(ARRAY_BUFFER));
+        view(&mut buffer, floats(self.uvs().iter().flat_map(|t| t.iter())), Some(ARRAY_BUFFER));
+        let indices = self.triangles().iter().flat_map(|t| t.iter()).flat_map(|i| i.to_le_bytes().to_vec());
+        view(&mut buffer, indices.collect(), Some(ELEMENT_ARRAY_BUFFER));
+        view(&mut buffer, png_bytes(texture)?, None);
+
+        let (min, max) = self.positions()
+            .iter()
+            .fold(([::std::f64::INFINITY; 3], [::std::f64::NEG_INFINITY; 3]), |(min, max), p| {
+                ([min[0].min(p[0]), min[1].min(p[1]), min[2].min(p[2])],
+                 [max[0].max(p[0]), max[1].max(p[1]), max[2].max(p[2])])
+            });
+        let count = self.positions().len();
+
+        let bin = path.with_extension("bin");
+        let mut buffer_desc = json!({"byteLength": buffer.len()});
+        if !binary {
+            buffer_desc["uri"] = json!(bin.file_name().map(|f| f.to_string_lossy().into_owned()));
+        }
+
+        let gltf = json!({
+            "asset": {"version": "2.0", "generator": "map-generator"},
+            "scene": 0,
+            "scenes": [{"nodes": [0]}],
+            "nodes": [{"mesh": 0}],
+            "meshes": [{
+                "primitives": [{
+                    "attributes": {"POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2},
+                    "indices": 3,
+                    "material": 0
+                }]
+            }],
+            "materials": [{
+                "pbrMetallicRoughness": {
+                    "baseColorTexture": {"index": 0},
+                    "metallicFactor": 0.0,
+                    "roughnessFactor": 1.0
+                }
+            }],
+            "textures": [{"sampler": 0, "source": 0}],
+            "samplers": [{
+                "magFilter": LINEAR,
+                "minFilter": LINEAR_MIPMAP_LINEAR,
+                "wrapS": CLAMP_TO_EDGE,
+                "wrapT": CLAMP_TO_EDGE
+            }],
+            "images": [{"bufferView": 4, "mimeType": "image/png"}],
+            "accessors": [
+                {"bufferView": 0, "componentType": FLOAT, "count": count, "type": "VEC3",
+                 "min": min.to_vec(), "max": max.to_vec()},
+                {"bufferView": 1, "componentType": FLOAT, "count": count, "type": "VEC3"},
+                {"bufferView": 2, "componentType": FLOAT, "count": count, "type": "VEC2"},
+                {"bufferView": 3, "componentType": UNSIGNED_INT, "count": 3 * self.triangles().len(),
+                 "type": "SCALAR"}
+            ],
+            "bufferViews": views,
+            "buffers": [buffer_desc]
+        });
+        let mut json = serde_json::to_vec(&gltf).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+        let mut w = BufWriter::new(File::create(path)?);
+        if binary {
+            pad(&mut json, b' ');
+            let length = 12 + 8 + json.len() + 8 + buffer.len();
+
+            w.write_all(b"glTF")?;
+            w.write_all(&2u32.to_le_bytes())?;
+            w.write_all(&(length as u32).to_le_bytes())?;
+            w.write_all(&(json.len() as u32).to_le_bytes())?;
+            w.write_all(b"JSON")?;
+            w.write_all(&json)?;
+            w.write_all(&(buffer.len() as u32).to_le_bytes())?;
+            w.write_all(b"BIN\0")?;
+            w.write_all(&buffer)?;
+        } else {
+            w.write_all(&json)?;
+            File::create(bin)?.write_all(&buffer)?;
+        }
+
+        w.flush()
+    }
+}
@@ -0,0 +1,17 @@
+extern crate heightmap;
+extern crate image;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+
+mod gltf;
+mod lod;
+mod mesh;
+mod obj;
+mod rtin;
+mod stl;
+
+pub use lod::{LodOptions, LodTile, Pyramid};
+pub use mesh::{Mesh, MeshOptions};
@@ -0,0 +1,223 @@
+//! Quadtree of mesh tiles at several levels of detail, for streaming terrain.
+//!
+//! Level `0` is a single tile covering the map, each level splits the tiles of the previous one in
+//! four and halves their error. Tiles are in the coordinates of the whole mesh and neighbours of the
+//! same level share their borders, skirts hiding the cracks between levels.
+use heightmap::{Heightmap, Sample};
+use image::{Rgb, RgbImage};
+use mesh::{Mesh, MeshOptions};
+use rtin::{Rtin, build};
+use serde::{Deserialize, Deserializer};
+use serde::de::Error;
+use serde_json;
+use std::io;
+use std::io::Write;
+
+/// Deepest quadtree accepted, the last level then having `4^15` tiles.
+const MAX_LEVELS: u32 = 16;
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct LodOptions {
+    /// Number of levels of the quadtree, from `1` to `16`.
+    pub levels: u32,
+    /// Largest vertical distance between the tiles of the last level and the map.
+    pub max_error: f64,
+    /// Depth of the walls added below the borders of the tiles, twice their error when not set.
+    pub skirt: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct LodFields {
+    #[serde(default = "default_levels")]
+    levels: u32,
+    #[serde(default = "default_max_error")]
+    max_error: f64,
+    #[serde(default)]
+    skirt: Option<f64>,
+}
+
+fn default_levels() -> u32 {
+    4
+}
+
+fn default_max_error() -> f64 {
+    0.001
+}
+
+impl Deserialize for LodOptions {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer
+    {
+        let fields = LodFields::deserialize(deserializer)?;
+        if fields.levels == 0 || fields.levels > MAX_LEVELS {
+            return Err(D::Error::custom(format!("levels must be from 1 to {}", MAX_LEVELS)));
+        }
+
+        Ok(LodOptions {
+               levels: fields.levels,
+               max_error: fields.max_error,
+               skirt: fields.skirt,
+           })
+    }
+}
+
+impl Default for LodOptions {
+    fn default() -> LodOptions {
+        LodOptions {
+            levels: default_levels(),
+            max_error: default_max_error(),
+            skirt: None,
+        }
+    }
+}
+
+/// Entry of the index of a pyramid.
+#[derive(Clone, Debug, Serialize)]
+pub struct LodTile {
+    pub level: u32,
+    pub x: u32,
+    pub y: u32,
+    pub file: String,
+    /// Largest vertical distance between the tile and the map.
+    pub error: f64,
+    /// Bounding box of the tile surface, without its skirt.
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+    pub triangles: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct LodIndex<'a> {
+    levels: u32,
+    horizontal_scale: f64,
+    vertical_scale: f64,
+    tiles: &'a [LodTile],
+}
+
+pub struct Pyramid<'a, T: 'a> {
+    hmap: &'a Heightmap<T>,
+    rtin: Rtin<'a, T>,
+    options: MeshOptions,
+    lod: LodOptions,
+}
+
+impl<'a, T> Pyramid<'a, T>
+    where T: Sample
+{
+    pub fn new(hmap: &'a Heightmap<T>, options: &MeshOptions, lod: &LodOptions) -> Pyramid<'a, T> {
+        Pyramid {
+            hmap,
+            rtin: Rtin::new(hmap),
+            options: *options,
+            lod: *lod,
+        }
+    }
+
+    pub fn levels(&self) -> u32 {
+        self.lod.levels
+    }
+
+    /// Tiles of a level covering at least a part of the map.
+    pub fn tiles(&self, level: u32) -> Vec<(u32, u32)> {
+        let side = self.side(level);
+        let count = |size: u32| (size - 1 + side - 1) / side;
+        let (width, height) = (count(self.hmap.width()), count(self.hmap.height()));
+
+        (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).collect()
+    }
+
+    /// Largest vertical distance between the tiles of a level and the map.
+    pub fn error(&self, level: u32) -> f64 {
+        self.lod.max_error * 2f64.powi((self.lod.levels - 1 - level) as i32)
+    }
+
+    /// Samples covered by a tile, as the top left and bottom right corners.
+    fn bounds(&self, level: u32, x: u32, y: u32) -> ((u32, u32), (u32, u32)) {
+        let side = self.side(level);
+        ((x * side, y * side),
+         (((x + 1) * side).min(self.hmap.width() - 1), ((y + 1) * side).min(self.hmap.height() - 1)))
+    }
+
+    fn side(&self, level: u32) -> u32 {
+        (self.rtin.side() >> level).max(1)
+    }
+
+    /// Mesh of a tile, its texture coordinates covering the tile only.
+    pub fn tile(&self, level: u32, x: u32, y: u32) -> io::Result<Mesh> {
+        let side = self.side(level);
+        let error = self.error(level);
+        let mut triangles = Vec::new();
+        self.rtin.tile_triangles(error / self.options.vertical_scale,
+                                 (x * side, y * side),
+                                 side,
+                                 &mut |a, b, c| triangles.push([a, b, c]));
+        let mut mesh = build(self.hmap, &self.options, &triangles)?;
+
+        let ((x0, y0), (x1, y1)) = self.bounds(level, x, y);
+        let (w, h) = (f64::from(self.hmap.width() - 1), f64::from(self.hmap.height() - 1));
+        mesh.crop_uvs([f64::from(x0) / w, f64::from(y0) / h], [f64::from(x1) / w, f64::from(y1) / h]);
+        mesh.add_skirt(self.lod.skirt.unwrap_or(2.0 * error));
+
+        Ok(mesh)
+    }
+
+    /// Part of the render of the whole map covered by a tile, downscaled so that the textures of
+    /// all levels have about the same size.
+    pub fn texture(&self, img: &RgbImage, level: u32, x: u32, y: u32) -> RgbImage {
+        let ((x0, y0), (x1, y1)) = self.bounds(level, x, y);
+        let step = 1 << (self.lod.levels - 1 - level);
+        let (width, height) = ((x1 - x0) / step + 1, (y1 - y0) / step + 1);
+
+        RgbImage::from_fn(width, height, |i, j| {
+            let (left, top) = (x0 + i * step, y0 + j * step);
+            let (right, bottom) = ((left + step).min(x1 + 1), (top + step).min(y1 + 1));
+
+            let mut sum = [0u32; 3];
+            for v in top..bottom {
+                for u in left..right {
+                    let p = img.get_pixel(u, v);
+                    for c in 0..3 {
+                        sum[c] += u32::from(p.data[c]);
+                    }
+                }
+            }
+            let n = (right - left) * (bottom - top);
+
+            Rgb([(sum[0] / n) as u8, (sum[1] / n) as u8, (sum[2] / n) as u8])
+        })
+    }
+
+    /// Index entry of a tile written to `file`.
+    pub fn entry(&self, level: u32, x: u32, y: u32, file: String, mesh: &Mesh) -> LodTile {
+        let ((x0, y0), (x1, y1)) = self.bounds(level, x, y);
+        let (low, high) = (0..y1 - y0 + 1)
+            .flat_map(|j| (0..x1 - x0 + 1).map(move |i| self.hmap.get(x0 + i, y0 + j)))
+            .fold((::std::f64::INFINITY, ::std::f64::NEG_INFINITY),
+                  |(low, high), h| (low.min(h), high.max(h)));
+        let (hs, vs) = (self.options.horizontal_scale, self.options.vertical_scale);
+
+        LodTile {
+            level,
+            x,
+            y,
+            file,
+            error: self.error(level),
+            min: [f64::from(x0) * hs, low * vs, f64::from(y0) * hs],
+            max: [f64::from(x1) * hs, high * vs, f64::from(y1) * hs],
+            triangles: mesh.triangles().len(),
+        }
+    }
+
+    /// Writes the JSON index of the tiles.
+    pub fn write_index<W: Write>(&self, mut w: W, tiles: &[LodTile]) -> io::Result<()> {
+        let index = LodIndex {
+            levels: self.lod.levels,
+            horizontal_scale: self.options.horizontal_scale,
+            vertical_scale: self.options.vertical_scale,
+            tiles,
+        };
+        serde_json::to_writer_pretty(&mut w, &index).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+        w.flush()
+    }
+}
@@ -0,0 +1,188 @@
+//! Triangle mesh of the surface of a `Heightmap`.
+//!
+//! Coordinates are right-handed with `y` pointing up: a sample at column `x` and row `z` of the map
+//! is at `(x * horizontal_scale, height * vertical_scale, z * horizontal_scale)`, so that a top view
+//! matches the rendered image.
+use heightmap::{Heightmap, Sample};
+use image::Normal;
+use std::collections::HashMap;
+use std::io;
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct MeshOptions {
+    /// Distance between two samples.
+    #[serde(default = "default_scale")]
+    pub horizontal_scale: f64,
+    /// Height of a sample at `1.0`.
+    #[serde(default = "default_scale")]
+    pub vertical_scale: f64,
+    /// Thickness of the solid base added below the lowest point of STL files.
+    #[serde(default)]
+    pub base: Option<f64>,
+    /// Largest vertical distance between the mesh and the map, simplifies the mesh when set.
+    #[serde(default)]
+    pub max_error: Option<f64>,
+    /// Largest number of triangles, simplifies the mesh when set.
+    #[serde(default)]
+    pub max_triangles: Option<usize>,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+impl Default for MeshOptions {
+    fn default() -> MeshOptions {
+        MeshOptions {
+            horizontal_scale: default_scale(),
+            vertical_scale: default_scale(),
+            base: None,
+            max_error: None,
+            max_triangles: None,
+        }
+    }
+}
+
+pub struct Mesh {
+    positions: Vec<[f64; 3]>,
+    normals: Vec<[f64; 3]>,
+    uvs: Vec<[f64; 2]>,
+    triangles: Vec<[u32; 3]>,
+}
+
+impl Mesh {
+    /// Simplified mesh if the options bound its error or size, full grid otherwise.
+    pub fn new<T>(hmap: &Heightmap<T>, options: &MeshOptions) -> io::Result<Mesh>
+        where T: Sample
+    {
+        if options.max_error.is_some() || options.max_triangles.is_some() {
+            Mesh::simplified(hmap, options)
+        } else {
+            Mesh::grid(hmap, options)
+        }
+    }
+
+    /// Two triangles per cell of the map.
+    pub fn grid<T>(hmap: &Heightmap<T>, options: &MeshOptions) -> io::Result<Mesh>
+        where T: Sample
+    {
+        let (width, height) = (hmap.width(), hmap.height());
+        let vertices = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).collect::<Vec<_>>();
+
+        let (columns, rows) = (width.saturating_sub(1), height.saturating_sub(1));
+        let mut triangles = Vec::with_capacity(2 * columns as usize * rows as usize);
+        for y in 0..rows {
+            for x in 0..columns {
+                let a = y * width + x;
+                let b = a + 1;
+                let c = a + width;
+                let d = c + 1;
+
+                triangles.push([a, c, b]);
+                triangles.push([b, c, d]);
+            }
+        }
+
+        Mesh::from_samples(hmap, options, &vertices, triangles)
+    }
+
+    /// Builds a mesh whose vertices are samples of the map, `triangles` indexing `vertices`.
+    ///
+    /// Fails on maps narrower or shorter than two samples, which have no surface.
+    pub fn from_samples<T>(hmap: &Heightmap<T>,
+                           options: &MeshOptions,
+                           vertices: &[(u32, u32)],
+                           triangles: Vec<[u32; 3]>)
+                           -> io::Result<Mesh>
+        where T: Sample
+    {
+        if hmap.width() < 2 || hmap.height() < 2 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Meshes need maps of at least 2x2 samples"));
+        }
+
+        let factor = options.vertical_scale / options.horizontal_scale;
+        let u = |x: u32| f64::from(x) / f64::from(hmap.width() - 1);
+        let v = |y: u32| f64::from(y) / f64::from(hmap.height() - 1);
+
+        Ok(Mesh {
+            positions: vertices
+                .iter()
+                .map(|&(x, y)| {
+                         [f64::from(x) * options.horizontal_scale,
+                          hmap.get(x, y) * options.vertical_scale,
+                          f64::from(y) * options.horizontal_scale]
+                     })
+                .collect(),
+            normals: vertices
+                .iter()
+                .map(|&(x, y)| {
+                         let n = hmap.scaled_normal(x, y, factor);
+                         [n.x(), n.z(), n.y()]
+                     })
+                .collect(),
+            uvs: vertices.iter().map(|&(x, y)| [u(x), v(y)]).collect(),
+            triangles,
+        })
+    }
+
+    pub fn positions(&self) -> &[[f64; 3]] {
+        &self.positions
+    }
+
+    pub fn normals(&self) -> &[[f64; 3]] {
+        &self.normals
+    }
+
+    /// Texture coordinates, `(0, 0)` being the top left corner of the map.
+    pub fn uvs(&self) -> &[[f64; 2]] {
+        &self.uvs
+    }
+
+    /// Counter-clockwise when seen from above.
+    pub fn triangles(&self) -> &[[u32; 3]] {
+        &self.triangles
+    }
+
+    /// Maps texture coordinates so that the area of the map from `min` to `max` covers the texture.
+    pub fn crop_uvs(&mut self, min: [f64; 2], max: [f64; 2]) {
+        for t in &mut self.uvs {
+            *t = [(t[0] - min[0]) / (max[0] - min[0]), (t[1] - min[1]) / (max[1] - min[1])];
+        }
+    }
+
+    /// Adds vertical walls of the given depth below the boundary, to hide cracks between meshes.
+    pub fn add_skirt(&mut self, depth: f64) {
+        let mut lowered = HashMap::new();
+        for (p, q) in self.boundary() {
+            let mut lower = |i: u32| {
+                *lowered.entry(i).or_insert_with(|| {
+                    let i = i as usize;
+                    let v = self.positions[i];
+                    self.positions.push([v[0], v[1] - depth, v[2]]);
+                    self.normals.push(self.normals[i]);
+                    self.uvs.push(self.uvs[i]);
+                    self.positions.len() as u32 - 1
+                })
+            };
+            let (p2, q2) = (lower(p), lower(q));
+
+            self.triangles.push([q, p, p2]);
+            self.triangles.push([q, p2, q2]);
+        }
+    }
+
+    /// Edges belonging to a single triangle, in the order of that triangle.
+    pub fn boundary(&self) -> Vec<(u32, u32)> {
+        let mut edges = HashMap::new();
+        for t in &self.triangles {
+            for &(a, b) in &[(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+                edges.entry((a.min(b), a.max(b))).or_insert(((a, b), 0)).1 += 1;
+            }
+        }
+
+        let mut boundary = edges.values().filter(|&&(_, n)| n == 1).map(|&(e, _)| e).collect::<Vec<_>>();
+        boundary.sort();
+
+        boundary
+    }
+}
@@ -0,0 +1,28 @@
+//! Wavefront OBJ export.
+use mesh::Mesh;
+use std::io;
+use std::io::Write;
+
+impl Mesh {
+    /// Writes positions, texture coordinates and normals, faces sharing the same index for all three.
+    pub fn write_obj<W: Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "# {} vertices, {} triangles", self.positions().len(), self.triangles().len())?;
+
+        for p in self.positions() {
+            writeln!(w, "v {} {} {}", p[0], p[1], p[2])?;
+        }
+        // OBJ texture coordinates start from the bottom left corner
+        for t in self.uvs() {
+            writeln!(w, "vt {} {}", t[0], 1.0 - t[1])?;
+        }
+        for n in self.normals() {
+            writeln!(w, "vn {} {} {}", n[0], n[1], n[2])?;
+        }
+        for t in self.triangles() {
+            let (a, b, c) = (t[0] + 1, t[1] + 1, t[2] + 1);
+            writeln!(w, "f {}/{}/{} {}/{}/{} {}/{}/{}", a, a, a, b, b, b, c, c, c)?;
+        }
+
+        w.flush()
+    }
+}
@@ -0,0 +1,381 @@
+//! Right-triangulated irregular network: adaptive triangulation of a `Heightmap`.
+//!
+//! The map is covered by a square of `2^k + 1` samples recursively split into right triangles.
+//! The error of a triangle is the largest vertical distance between the samples it covers and its
+//! plane. It is stored at the middle of the hypotenuse, shared with the neighbour that must be split
+//! along with it, and propagated to the parents, so that a mesh without cracks can be extracted for
+//! any error bound.
+use heightmap::{Heightmap, Sample};
+use mesh::{Mesh, MeshOptions};
+use std::collections::HashMap;
+use std::io;
+
+pub type Point = (u32, u32);
+
+pub struct Rtin<'a, T: 'a> {
+    hmap: &'a Heightmap<T>,
+    size: u32,
+    errors: Vec<f64>,
+}
+
+impl<'a, T> Rtin<'a, T>
+    where T: Sample
+{
+    pub fn new(hmap: &'a Heightmap<T>) -> Rtin<'a, T> {
+        let side = ::std::cmp::max(hmap.width(), hmap.height()) - 1;
+        let tile = side.next_power_of_two();
+        let size = tile + 1;
+
+        let mut rtin = Rtin {
+            hmap,
+            size,
+            errors: vec![0.0; size as usize * size as usize],
+        };
+
+        // Children have higher ids than their parent
+        let count = 2 * tile as usize * tile as usize - 2;
+        let parents = count - tile as usize * tile as usize;
+        for i in (0..count).rev() {
+            let (a, b, c) = rtin.coordinates(i + 2, tile);
+            let m = middle(a, b);
+
+            let error = if rtin.inside(&[a, b, c]) {
+                rtin.error(a, b, c)
+            } else if rtin.outside(&[a, b, c]) {
+                0.0
+            } else {
+                // Split down to the cells crossed by the border of the map
+                ::std::f64::INFINITY
+            };
+
+            let mut e = rtin.errors[rtin.index(m)].max(error);
+            if i < parents {
+                e = e.max(rtin.errors[rtin.index(middle(a, c))]).max(rtin.errors[rtin.index(middle(b, c))]);
+            }
+            let index = rtin.index(m);
+            rtin.errors[index] = e;
+        }
+
+        rtin
+    }
+
+    /// Triangle with the given id, the first two ones splitting the whole square along its diagonal.
+    fn coordinates(&self, mut id: usize, tile: u32) -> (Point, Point, Point) {
+        let (mut a, mut b, mut c) = if id & 1 == 1 {
+            ((0, 0), (tile, tile), (tile, 0))
+        } else {
+            ((tile, tile), (0, 0), (0, tile))
+        };
+
+        loop {
+            id >>= 1;
+            if id <= 1 {
+                break;
+            }
+
+            let m = middle(a, b);
+            if id & 1 == 1 {
+                b = a;
+                a = c;
+            } else {
+                a = b;
+                b = c;
+            }
+            c = m;
+        }
+
+        (a, b, c)
+    }
+
+    fn index(&self, (x, y): Point) -> usize {
+        y as usize * self.size as usize + x as usize
+    }
+
+    fn inside(&self, points: &[Point]) -> bool {
+        points.iter().all(|&(x, y)| x < self.hmap.width() && y < self.hmap.height())
+    }
+
+    fn outside(&self, points: &[Point]) -> bool {
+        points.iter().all(|&(x, _)| x + 1 >= self.hmap.width()) ||
+        points.iter().all(|&(_, y)| y + 1 >= self.hmap.height())
+    }
+
+    /// Largest distance between the samples covered by the triangle and its plane.
+    fn error(&self, a: Point, b: Point, c: Point) -> f64 {
+        let edge = |p: Point, q: Point, x: i64, y: i64| {
+            (i64::from(q.0) - i64::from(p.0)) * (y - i64::from(p.1)) -
+            (i64::from(q.1) - i64::from(p.1)) * (x - i64::from(p.0))
+        };
+        let area = edge(a, b, i64::from(c.0), i64::from(c.1));
+        let (ha, hb, hc) = (self.hmap.get(a.0, a.1), self.hmap.get(b.0, b.1), self.hmap.get(c.0, c.1));
+
+        let (x0, x1) = (a.0.min(b.0).min(c.0), a.0.max(b.0).max(c.0));
+        let (y0, y1) = (a.1.min(b.1).min(c.1), a.1.max(b.1).max(c.1));
+
+        let mut error = 0.0f64;
+        for y in y0..y1 + 1 {
+            for x in x0..x1 + 1 {
+                let (wa, wb, wc) = (edge(b, c, i64::from(x), i64::from(y)),
+                                    edge(c, a, i64::from(x), i64::from(y)),
+                                    edge(a, b, i64::from(x), i64::from(y)));
+                if wa * area >= 0 && wb * area >= 0 && wc * area >= 0 {
+                    let h = (wa as f64 * ha + wb as f64 * hb + wc as f64 * hc) / area as f64;
+                    error = error.max((h - self.hmap.get(x, y)).abs());
+                }
+            }
+        }
+
+        error
+    }
+
+    /// Side of the square covered by the triangulation, a power of two.
+    pub fn side(&self) -> u32 {
+        self.size - 1
+    }
+
+    /// Calls `f` on each triangle of the mesh approximating the map within `max_error`.
+    pub fn triangles<F>(&self, max_error: f64, f: &mut F)
+        where F: FnMut(Point, Point, Point)
+    {
+        self.tile_triangles(max_error, (0, 0), self.side(), f);
+    }
+
+    /// Same as `triangles` for the part of the mesh in the square at `origin`, triangles larger than
+    /// the square being split whatever their error. Squares whose side is `side() / 2^n` line up with
+    /// the triangles, so that the tiles of the same size cover the mesh.
+    pub fn tile_triangles<F>(&self, max_error: f64, origin: Point, side: u32, f: &mut F)
+        where F: FnMut(Point, Point, Point)
+    {
+        let tile = self.side();
+        let square = (origin, (origin.0 + side, origin.1 + side));
+        self.split((0, 0), (tile, tile), (tile, 0), max_error, square, f);
+        self.split((tile, tile), (0, 0), (0, tile), max_error, square, f);
+    }
+
+    fn split<F>(&self, a: Point, b: Point, c: Point, max_error: f64, square: (Point, Point), f: &mut F)
+        where F: FnMut(Point, Point, Point)
+    {
+        let (min, max) = ((a.0.min(b.0).min(c.0), a.1.min(b.1).min(c.1)),
+                          (a.0.max(b.0).max(c.0), a.1.max(b.1).max(c.1)));
+        let ((x0, y0), (x1, y1)) = square;
+        if self.outside(&[a, b, c]) || min.0 >= x1 || max.0 <= x0 || min.1 >= y1 || max.1 <= y0 {
+            return;
+        }
+
+        let m = middle(a, b);
+        let leg = (i64::from(a.0) - i64::from(c.0)).abs() + (i64::from(a.1) - i64::from(c.1)).abs();
+        let larger = min.0 < x0 || max.0 > x1 || min.1 < y0 || max.1 > y1;
+        if leg > 1 && (larger || self.errors[self.index(m)] > max_error) {
+            self.split(c, a, m, max_error, square, f);
+            self.split(b, c, m, max_error, square, f);
+        } else {
+            f(a, b, c);
+        }
+    }
+
+    fn count(&self, max_error: f64) -> usize {
+        let mut count = 0;
+        self.triangles(max_error, &mut |_, _, _| count += 1);
+
+        count
+    }
+
+    /// Smallest error bound, not lower than `min_error`, giving at most `max_triangles` triangles.
+    ///
+    /// Fails when no bound does: the mesh never has fewer than two triangles, and the triangles
+    /// crossing the border of a map whose sides are not `2^k + 1` samples are always split.
+    fn bound(&self, min_error: f64, max_triangles: usize) -> io::Result<f64> {
+        let max = self.errors
+            .iter()
+            .cloned()
+            .filter(|e| e.is_finite())
+            .fold(0.0f64, f64::max);
+        let (mut low, mut high) = (min_error, max.max(min_error));
+        if self.count(low) <= max_triangles {
+            return Ok(low);
+        }
+
+        for _ in 0..32 {
+            let error = (low + high) / 2.0;
+            if self.count(error) <= max_triangles {
+                high = error;
+            } else {
+                low = error;
+            }
+        }
+
+        let fewest = self.count(high);
+        if fewest > max_triangles {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      format!("The mesh needs at least {} triangles, over the budget of {}",
+                                              fewest,
+                                              max_triangles)));
+        }
+
+        Ok(high)
+    }
+}
+
+fn middle(a: Point, b: Point) -> Point {
+    ((a.0 + b.0) / 2, (a.1 + b.1) / 2)
+}
+
+/// Builds a mesh from triangles of samples, sharing their vertices.
+pub fn build<T>(hmap: &Heightmap<T>, options: &MeshOptions, triangles: &[[Point; 3]]) -> io::Result<Mesh>
+    where T: Sample
+{
+    let mut indices = HashMap::new();
+    let mut vertices = Vec::new();
+    let faces = triangles
+        .iter()
+        .map(|t| {
+            let mut face = [0; 3];
+            for (i, &p) in t.iter().enumerate() {
+                face[i] = *indices.entry(p).or_insert_with(|| {
+                    vertices.push(p);
+                    vertices.len() as u32 - 1
+                });
+            }
+            face
+        })
+        .collect();
+
+    Mesh::from_samples(hmap, options, &vertices, faces)
+}
+
+impl Mesh {
+    /// Mesh with fewer triangles where the map is flat, as bounded by the `max_error` and
+    /// `max_triangles` options.
+    pub fn simplified<T>(hmap: &Heightmap<T>, options: &MeshOptions) -> io::Result<Mesh>
+        where T: Sample
+    {
+        let rtin = Rtin::new(hmap);
+
+        // Errors are measured on the map, options are in mesh units
+        let min_error = options.max_error.unwrap_or(0.0) / options.vertical_scale;
+        let max_error = match options.max_triangles {
+            Some(n) => rtin.bound(min_error, n)?,
+            None => min_error,
+        };
+
+        let mut triangles = Vec::new();
+        rtin.triangles(max_error, &mut |a, b, c| triangles.push([a, b, c]));
+
+        build(hmap, options, &triangles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use heightmap::heightmap_from_iter;
+
+    /// Hills and a ridge, neither flat nor a single plane.
+    fn terrain(width: u32, height: u32) -> Heightmap {
+        heightmap_from_iter(width,
+                            height,
+                            (0..width * height).map(|i| {
+                                let (x, y) = (f64::from(i % width) / 7.0, f64::from(i / width) / 5.0);
+                                0.5 + 0.25 * (x.sin() * y.cos()) + 0.1 * (x * 0.3 + y).sin().abs()
+                            }))
+    }
+
+    /// Largest distance between the samples and the triangle covering them, panicking when a sample
+    /// is covered by no triangle.
+    fn max_distance(hmap: &Heightmap, triangles: &[[Point; 3]]) -> f64 {
+        let mut covered = vec![false; hmap.width() as usize * hmap.height() as usize];
+        let mut distance = 0.0f64;
+
+        for &[a, b, c] in triangles {
+            let edge = |p: Point, q: Point, x: i64, y: i64| {
+                (i64::from(q.0) - i64::from(p.0)) * (y - i64::from(p.1)) -
+                (i64::from(q.1) - i64::from(p.1)) * (x - i64::from(p.0))
+            };
+            let area = edge(a, b, i64::from(c.0), i64::from(c.1));
+            for y in a.1.min(b.1).min(c.1)..a.1.max(b.1).max(c.1) + 1 {
+                for x in a.0.min(b.0).min(c.0)..a.0.max(b.0).max(c.0) + 1 {
+                    let (px, py) = (i64::from(x), i64::from(y));
+                    let (wa, wb, wc) = (edge(b, c, px, py), edge(c, a, px, py), edge(a, b, px, py));
+                    if wa * area < 0 || wb * area < 0 || wc * area < 0 {
+                        continue;
+                    }
+
+                    let h = (wa as f64 * hmap.get(a.0, a.1) + wb as f64 * hmap.get(b.0, b.1) +
+                             wc as f64 * hmap.get(c.0, c.1)) / area as f64;
+                    distance = distance.max((h - hmap.get(x, y)).abs());
+                    covered[(y * hmap.width() + x) as usize] = true;
+                }
+            }
+        }
+
+        assert!(covered.iter().all(|&c| c), "Samples left out of the mesh");
+        distance
+    }
+
+    #[test]
+    fn samples_are_within_the_error_bound() {
+        for &(width, height) in &[(33, 33), (40, 23)] {
+            let hmap = terrain(width, height);
+            let rtin = Rtin::new(&hmap);
+
+            for &max_error in &[0.0, 0.001, 0.01, 0.05, 0.2, 1.0] {
+                let mut triangles = Vec::new();
+                rtin.triangles(max_error, &mut |a, b, c| triangles.push([a, b, c]));
+
+                let distance = max_distance(&hmap, &triangles);
+                assert!(distance <= max_error + 1e-12,
+                        "{}x{} map: {} over the bound {}",
+                        width,
+                        height,
+                        distance,
+                        max_error);
+            }
+        }
+    }
+
+    #[test]
+    fn triangle_budget_is_met() {
+        let hmap = terrain(65, 65);
+        let mut previous = 0;
+
+        for &budget in &[2, 10, 100, 1000, 10000] {
+            let options = MeshOptions {
+                max_triangles: Some(budget),
+                ..MeshOptions::default()
+            };
+            let triangles = Mesh::simplified(&hmap, &options).unwrap().triangles().len();
+
+            assert!(triangles <= budget, "{} triangles over the budget of {}", triangles, budget);
+            assert!(triangles >= previous, "A larger budget gave a coarser mesh");
+            previous = triangles;
+        }
+
+        // The budget is large enough for the full grid
+        assert_eq!(previous, 2 * 64 * 64);
+    }
+
+    #[test]
+    fn budgets_below_the_fewest_triangles_are_errors() {
+        for &(width, height) in &[(65, 65), (40, 23)] {
+            let hmap = terrain(width, height);
+            let fewest = Rtin::new(&hmap).count(::std::f64::MAX);
+
+            for &budget in &[1, fewest - 1, fewest] {
+                let options = MeshOptions {
+                    max_triangles: Some(budget),
+                    ..MeshOptions::default()
+                };
+                match Mesh::simplified(&hmap, &options) {
+                    Ok(mesh) => {
+                        assert!(budget >= fewest, "{}x{} map: mesh over the budget of {}", width, height, budget);
+                        assert!(mesh.triangles().len() <= budget);
+                    }
+                    Err(e) => {
+                        assert!(budget < fewest, "{}x{} map: budget of {} rejected", width, height, budget);
+                        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! Binary STL export, for 3D printing.
+use mesh::Mesh;
+use std::io;
+use std::io::Write;
+
+type Triangle = [[f64; 3]; 3];
+
+fn normal(t: &Triangle) -> [f64; 3] {
+    let u = [t[1][0] - t[0][0], t[1][1] - t[0][1], t[1][2] - t[0][2]];
+    let v = [t[2][0] - t[0][0], t[2][1] - t[0][1], t[2][2] - t[0][2]];
+    let n = [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]];
+    let l = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+
+    if l > 0.0 {
+        [n[0] / l, n[1] / l, n[2] / l]
+    } else {
+        n
+    }
+}
+
+fn write_vector<W: Write>(w: &mut W, v: [f64; 3]) -> io::Result<()> {
+    // STL files are z-up
+    for &c in &[v[0], -v[2], v[1]] {
+        w.write_all(&(c as f32).to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+impl Mesh {
+    /// Writes the surface, closed by walls and a flat bottom `base` below its lowest point if set.
+    pub fn write_stl<W: Write>(&self, mut w: W, base: Option<f64>) -> io::Result<()> {
+        let p = self.positions();
+        let mut triangles = self.triangles()
+            .iter()
+            .map(|t| [p[t[0] as usize], p[t[1] as usize], p[t[2] as usize]])
+            .collect::<Vec<Triangle>>();
+
+        if let Some(thickness) = base {
+            let bottom = p.iter().map(|v| v[1]).fold(::std::f64::INFINITY, f64::min) - thickness;
+            let below = |v: [f64; 3]| [v[0], bottom, v[2]];
+
+            let boundary = self.boundary();
+            let count = boundary.len() as f64;
+            let center = boundary
+                .iter()
+                .fold([0.0, bottom, 0.0], |c, &(a, _)| {
+                    [c[0] + p[a as usize][0] / count, bottom, c[2] + p[a as usize][2] / count]
+                });
+
+            for &(a, b) in &boundary {
+                let (a, b) = (p[a as usize], p[b as usize]);
+                triangles.push([b, a, below(a)]);
+                triangles.push([b, below(a), below(b)]);
+                triangles.push([center, below(b), below(a)]);
+            }
+        }
+
+        w.write_all(&[0; 80])?;
+        w.write_all(&(triangles.len() as u32).to_le_bytes())?;
+        for t in &triangles {
+            write_vector(&mut w, normal(t))?;
+            for &v in t {
+                write_vector(&mut w, v)?;
+            }
+            w.write_all(&[0; 2])?;
+        }
+
+        w.flush()
+    }
+}
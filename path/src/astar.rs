@@ -0,0 +1,104 @@
+//! Shortest paths between the samples of a map, moving to any of the 8 neighbours.
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::f64;
+
+pub type Point = (u32, u32);
+
+/// Neighbours of a sample inside a map of the given size, with the length of the move.
+pub fn neighbours((x, y): Point, width: u32, height: u32) -> Vec<(Point, f64)> {
+    let mut neighbours = Vec::with_capacity(8);
+    for dy in -1i64..2 {
+        for dx in -1i64..2 {
+            let (nx, ny) = (i64::from(x) + dx, i64::from(y) + dy);
+            if (dx != 0 || dy != 0) && nx >= 0 && ny >= 0 && nx < i64::from(width) && ny < i64::from(height) {
+                let length = if dx != 0 && dy != 0 { f64::consts::SQRT_2 } else { 1.0 };
+                neighbours.push(((nx as u32, ny as u32), length));
+            }
+        }
+    }
+
+    neighbours
+}
+
+/// Entry of the open set, the smallest estimate first.
+#[derive(PartialEq)]
+struct Open {
+    estimate: f64,
+    index: usize,
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Open) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Open) -> Ordering {
+        other.estimate.partial_cmp(&self.estimate).unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Cheapest path from `start` to `goal`, both included, or `None` if the goal cannot be reached.
+///
+/// `cost(from, to, length)` is the cost of a move between neighbours, `None` if it is forbidden. It
+/// must be at least `min_cost * length` for the path to be the cheapest.
+pub fn astar<F>(width: u32, height: u32, start: Point, goal: Point, min_cost: f64, cost: F) -> Option<Vec<Point>>
+    where F: Fn(Point, Point, f64) -> Option<f64>
+{
+    let index = |(x, y): Point| y as usize * width as usize + x as usize;
+    let point = |i: usize| ((i % width as usize) as u32, (i / width as usize) as u32);
+    let heuristic = |(x, y): Point| {
+        let (dx, dy) = ((f64::from(x) - f64::from(goal.0)).abs(), (f64::from(y) - f64::from(goal.1)).abs());
+        // Octile distance: diagonal moves then straight ones
+        min_cost * (dx.max(dy) + (f64::consts::SQRT_2 - 1.0) * dx.min(dy))
+    };
+
+    let mut costs = vec![f64::INFINITY; width as usize * height as usize];
+    let mut parents = vec![usize::max_value(); costs.len()];
+    let mut open = BinaryHeap::new();
+
+    costs[index(start)] = 0.0;
+    open.push(Open {
+                  estimate: heuristic(start),
+                  index: index(start),
+              });
+
+    while let Some(Open { estimate, index: current }) = open.pop() {
+        let p = point(current);
+        if p == goal {
+            let mut path = vec![goal];
+            let mut i = current;
+            while parents[i] != usize::max_value() {
+                i = parents[i];
+                path.push(point(i));
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if estimate > costs[current] + heuristic(p) {
+            // Already reached through a cheaper path
+            continue;
+        }
+
+        for (n, length) in neighbours(p, width, height) {
+            if let Some(c) = cost(p, n, length) {
+                let next = costs[current] + c;
+                let i = index(n);
+                if next < costs[i] {
+                    costs[i] = next;
+                    parents[i] = current;
+                    open.push(Open {
+                                  estimate: next + heuristic(n),
+                                  index: i,
+                              });
+                }
+            }
+        }
+    }
+
+    None
+}
@@ -0,0 +1,67 @@
+//! Cost of the cheapest paths from a set of samples to all the others.
+use astar::{Point, neighbours};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::f64;
+
+/// Entry of the queue, the cheapest first.
+#[derive(PartialEq)]
+struct Queued {
+    cost: f64,
+    index: usize,
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Queued) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Queued) -> Ordering {
+        other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Cost of the cheapest path from any of the `sources` to each sample, row by row, infinite where no
+/// path exists. `cost` is as in `astar`.
+pub fn dijkstra<F>(width: u32, height: u32, sources: &[Point], cost: F) -> Vec<f64>
+    where F: Fn(Point, Point, f64) -> Option<f64>
+{
+    let index = |(x, y): Point| y as usize * width as usize + x as usize;
+    let point = |i: usize| ((i % width as usize) as u32, (i / width as usize) as u32);
+
+    let mut costs = vec![f64::INFINITY; width as usize * height as usize];
+    let mut queue = BinaryHeap::new();
+    for &s in sources {
+        costs[index(s)] = 0.0;
+        queue.push(Queued {
+                       cost: 0.0,
+                       index: index(s),
+                   });
+    }
+
+    while let Some(Queued { cost: c, index: current }) = queue.pop() {
+        if c > costs[current] {
+            continue;
+        }
+
+        let p = point(current);
+        for (n, length) in neighbours(p, width, height) {
+            if let Some(step) = cost(p, n, length) {
+                let i = index(n);
+                if c + step < costs[i] {
+                    costs[i] = c + step;
+                    queue.push(Queued {
+                                   cost: c + step,
+                                   index: i,
+                               });
+                }
+            }
+        }
+    }
+
+    costs
+}
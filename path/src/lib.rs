@@ -0,0 +1,12 @@
+extern crate heightmap;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+mod astar;
+mod dijkstra;
+mod terrain;
+
+pub use astar::{Point, astar, neighbours};
+pub use dijkstra::dijkstra;
+pub use terrain::{CostModel, Route, Terrain};
@@ -0,0 +1,201 @@
+//! Walking costs over a `Heightmap`.
+use astar::{Point, astar};
+use dijkstra::dijkstra;
+use heightmap::{Heightmap, Sample, heightmap_from_vec};
+use serde::{Deserialize, Deserializer};
+use serde::de::Error;
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct CostModel {
+    /// Cost of a move into water per distance, water being impassable when not set.
+    pub water: Option<f64>,
+    /// Steepest move allowed, in degrees.
+    pub max_slope: Option<f64>,
+    /// Extra cost per distance climbed.
+    pub uphill: f64,
+}
+
+#[derive(Deserialize)]
+struct CostFields {
+    #[serde(default)]
+    water: Option<f64>,
+    #[serde(default)]
+    max_slope: Option<f64>,
+    #[serde(default = "default_uphill")]
+    uphill: f64,
+}
+
+fn default_uphill() -> f64 {
+    1.0
+}
+
+impl Deserialize for CostModel {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer
+    {
+        // Negative costs would make the searches miss the cheapest paths
+        let fields = CostFields::deserialize(deserializer)?;
+        if fields.water.map_or(false, |w| !(w >= 0.0)) {
+            return Err(D::Error::custom("water cannot be negative"));
+        }
+        if !(fields.uphill >= 0.0) {
+            return Err(D::Error::custom("uphill cannot be negative"));
+        }
+
+        Ok(CostModel {
+               water: fields.water,
+               max_slope: fields.max_slope,
+               uphill: fields.uphill,
+           })
+    }
+}
+
+impl Default for CostModel {
+    fn default() -> CostModel {
+        CostModel {
+            water: None,
+            max_slope: None,
+            uphill: default_uphill(),
+        }
+    }
+}
+
+/// Cheapest path between two samples.
+#[derive(Clone, Debug)]
+pub struct Route {
+    /// Samples from the start to the goal, both included.
+    pub points: Vec<Point>,
+    pub cost: f64,
+}
+
+/// Map walked with a cost model, moves costing their length on flat ground.
+pub struct Terrain<'a, T: 'a> {
+    hmap: &'a Heightmap<T>,
+    scale: f64,
+    sea_level: f64,
+    model: CostModel,
+}
+
+impl<'a, T> Terrain<'a, T>
+    where T: Sample
+{
+    /// `scale` is the height of a sample at `1.0`, in distances between two samples, and the water
+    /// lies below `sea_level`.
+    pub fn new(hmap: &'a Heightmap<T>, scale: f64, sea_level: f64, model: &CostModel) -> Terrain<'a, T> {
+        Terrain {
+            hmap,
+            scale,
+            sea_level,
+            model: *model,
+        }
+    }
+
+    /// Cost of a move between neighbours `length` apart, `None` if it is not allowed.
+    pub fn cost(&self, from: Point, to: Point, length: f64) -> Option<f64> {
+        let (start, end) = (self.hmap.get(from.0, from.1), self.hmap.get(to.0, to.1));
+        let climb = (end.max(self.sea_level) - start.max(self.sea_level)) * self.scale;
+
+        if let Some(max) = self.model.max_slope {
+            if (climb.abs() / length).atan().to_degrees() > max {
+                return None;
+            }
+        }
+
+        let cost = if end < self.sea_level {
+            self.model.water? * length
+        } else {
+            length
+        };
+
+        Some(cost + self.model.uphill * climb.max(0.0))
+    }
+
+    /// Lower bound of the cost of a move per distance.
+    pub fn min_cost(&self) -> f64 {
+        self.model.water.map_or(1.0, |w| w.min(1.0))
+    }
+
+    /// Cheapest path from `start` to `goal`, `None` if the goal cannot be reached.
+    pub fn path(&self, start: Point, goal: Point) -> Option<Route> {
+        let points = astar(self.hmap.width(),
+                           self.hmap.height(),
+                           start,
+                           goal,
+                           self.min_cost(),
+                           |a, b, length| self.cost(a, b, length))?;
+        let cost = points
+            .windows(2)
+            .map(|s| {
+                let length = if s[0].0 != s[1].0 && s[0].1 != s[1].1 { 2.0f64.sqrt() } else { 1.0 };
+                self.cost(s[0], s[1], length).unwrap_or(0.0)
+            })
+            .sum();
+
+        Some(Route { points, cost })
+    }
+
+    /// Cost of the cheapest path from any of the `sources` to each sample, infinite where no path
+    /// exists.
+    pub fn distances(&self, sources: &[Point]) -> Heightmap {
+        let costs = dijkstra(self.hmap.width(),
+                             self.hmap.height(),
+                             sources,
+                             |a, b, length| self.cost(a, b, length));
+
+        heightmap_from_vec(self.hmap.width(), self.hmap.height(), costs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hills and a lake, with a ridge of impassable slopes when the slopes are limited.
+    fn map() -> Heightmap {
+        let (width, height) = (7, 6);
+        let heights = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| if x == 3 && y > 0 { 0.9 } else { ((x * 7 + y * 3) % 5) as f64 / 5.0 })
+            .collect();
+
+        heightmap_from_vec(width, height, heights)
+    }
+
+    #[test]
+    fn paths_cost_as_much_as_the_distances() {
+        let hmap = map();
+        let models = [CostModel::default(),
+                      CostModel {
+                          water: Some(3.0),
+                          uphill: 0.5,
+                          ..CostModel::default()
+                      },
+                      CostModel {
+                          water: Some(0.25),
+                          max_slope: Some(60.0),
+                          uphill: 2.0,
+                      }];
+        let start = (0, 5);
+
+        for model in &models {
+            let terrain = Terrain::new(&hmap, 4.0, 0.3, model);
+            let distances = terrain.distances(&[start]);
+            for y in 0..hmap.height() {
+                for x in 0..hmap.width() {
+                    match terrain.path(start, (x, y)) {
+                        Some(route) => {
+                            assert_eq!((route.points[0], route.points[route.points.len() - 1]), (start, (x, y)));
+                            assert!((route.cost - distances.get(x, y)).abs() < 1e-9,
+                                    "{:?} to {:?}: {} instead of {}",
+                                    start,
+                                    (x, y),
+                                    route.cost,
+                                    distances.get(x, y));
+                        }
+                        None => assert!(distances.get(x, y).is_infinite()),
+                    }
+                }
+            }
+        }
+    }
+}
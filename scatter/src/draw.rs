@@ -0,0 +1,37 @@
+//! Symbols of the objects drawn over a rendered map.
+use image::{Rgb, RgbImage};
+use rule::{ScatterRule, Symbol};
+use scatter::Placement;
+
+/// Draws the symbol of the rule of each object, objects without a rule being skipped.
+pub fn draw_objects(img: &mut RgbImage, objects: &[Placement], rules: &[ScatterRule]) {
+    for o in objects {
+        if let Some(rule) = rules.iter().find(|r| r.name == o.name) {
+            draw_symbol(img, rule.symbol, o.x, o.y, rule.size * o.scale, *rule.color);
+        }
+    }
+}
+
+fn draw_symbol(img: &mut RgbImage, symbol: Symbol, x: f64, y: f64, size: f64, color: Rgb<u8>) {
+    let size = size.max(0.5);
+    let (width, height) = img.dimensions();
+    let (x0, x1) = ((x - size).floor().max(0.0) as u32, (x + size).ceil().min(f64::from(width) - 1.0) as u32);
+    let (y0, y1) = ((y - size).floor().max(0.0) as u32, (y + size).ceil().min(f64::from(height) - 1.0) as u32);
+
+    for j in y0..y1 + 1 {
+        for i in x0..x1 + 1 {
+            // Position relative to the object, in sizes
+            let (u, v) = ((f64::from(i) - x) / size, (f64::from(j) - y) / size);
+            let inside = match symbol {
+                Symbol::Circle => u * u + v * v <= 1.0,
+                Symbol::Square => u.abs() <= 0.75 && v.abs() <= 0.75,
+                // Pointing to the top, base at the bottom
+                Symbol::Triangle => v <= 0.75 && v >= -1.0 && u.abs() <= (v + 1.0) / 1.75 * 0.9,
+                Symbol::Cross => (u.abs() <= 0.25 && v.abs() <= 1.0) || (v.abs() <= 0.25 && u.abs() <= 1.0),
+            };
+            if inside {
+                img.put_pixel(i, j, color);
+            }
+        }
+    }
+}
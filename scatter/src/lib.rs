@@ -0,0 +1,18 @@
+extern crate biome;
+extern crate heightmap;
+extern crate image;
+extern crate rand;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+mod draw;
+mod poisson;
+mod rule;
+mod scatter;
+
+pub use draw::draw_objects;
+pub use poisson::poisson_disk;
+pub use rule::{ScatterRule, Symbol};
+pub use scatter::{Layers, ObjectFormat, Placement, ScatterOptions};
@@ -0,0 +1,66 @@
+//! Poisson-disk sampling: random points no closer than a radius, without clusters nor gaps.
+use rand::Rng;
+use std::f64::consts::PI;
+
+/// Candidates tried around a point before it stops spawning new ones.
+const ATTEMPTS: u32 = 30;
+
+/// Points of the `[0, width) × [0, height)` rectangle at least `radius` apart (Bridson).
+pub fn poisson_disk<R: Rng>(width: f64, height: f64, radius: f64, rng: &mut R) -> Vec<(f64, f64)> {
+    if width <= 0.0 || height <= 0.0 || radius <= 0.0 {
+        return Vec::new();
+    }
+
+    // A cell holds at most one point
+    let cell = radius / 2.0f64.sqrt();
+    let (columns, rows) = ((width / cell).ceil() as usize, (height / cell).ceil() as usize);
+    let mut grid: Vec<Option<usize>> = vec![None; columns * rows];
+    let index = |(x, y): (f64, f64)| {
+        ((y / cell) as usize).min(rows - 1) * columns + ((x / cell) as usize).min(columns - 1)
+    };
+
+    let mut points = Vec::new();
+    let mut active = Vec::new();
+
+    let first = (rng.gen::<f64>() * width, rng.gen::<f64>() * height);
+    grid[index(first)] = Some(0);
+    points.push(first);
+    active.push(0);
+
+    while !active.is_empty() {
+        let i = rng.gen_range(0, active.len());
+        let (px, py) = points[active[i]];
+
+        let mut spawned = false;
+        for _ in 0..ATTEMPTS {
+            let angle = rng.gen::<f64>() * 2.0 * PI;
+            let distance = radius * (1.0 + rng.gen::<f64>());
+            let p = (px + distance * angle.cos(), py + distance * angle.sin());
+            if p.0 < 0.0 || p.0 >= width || p.1 < 0.0 || p.1 >= height {
+                continue;
+            }
+
+            let (cx, cy) = ((p.0 / cell) as usize, (p.1 / cell) as usize);
+            let far = (cy.saturating_sub(2)..(cy + 3).min(rows))
+                .flat_map(|y| (cx.saturating_sub(2)..(cx + 3).min(columns)).map(move |x| y * columns + x))
+                .filter_map(|c| grid[c])
+                .all(|n| {
+                    let (nx, ny) = points[n];
+                    (nx - p.0).hypot(ny - p.1) >= radius
+                });
+            if far {
+                grid[index(p)] = Some(points.len());
+                active.push(points.len());
+                points.push(p);
+                spawned = true;
+                break;
+            }
+        }
+
+        if !spawned {
+            active.swap_remove(i);
+        }
+    }
+
+    points
+}
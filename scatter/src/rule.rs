@@ -0,0 +1,101 @@
+//! Where objects of a kind grow, and how they are drawn.
+use biome::Range;
+use image::Color;
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::Error;
+
+/// Mark drawn on the map for an object.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Symbol {
+    Circle,
+    Triangle,
+    Square,
+    Cross,
+}
+
+impl Default for Symbol {
+    fn default() -> Symbol {
+        Symbol::Circle
+    }
+}
+
+impl Serialize for Symbol {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_str(match *self {
+                                     Symbol::Circle => "circle",
+                                     Symbol::Triangle => "triangle",
+                                     Symbol::Square => "square",
+                                     Symbol::Cross => "cross",
+                                 })
+    }
+}
+
+impl Deserialize for Symbol {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer
+    {
+        let string = String::deserialize(deserializer)?;
+        match string.as_str() {
+            "circle" => Ok(Symbol::Circle),
+            "triangle" => Ok(Symbol::Triangle),
+            "square" => Ok(Symbol::Square),
+            "cross" => Ok(Symbol::Cross),
+            s => Err(D::Error::unknown_variant(s, &["circle", "triangle", "square", "cross"])),
+        }
+    }
+}
+
+/// Objects of a kind, placed where all the ranges contain the values of the map.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ScatterRule {
+    pub name: String,
+    /// Smallest distance between an object of the rule and any other object, in samples.
+    pub spacing: f64,
+    /// Share of the suitable positions getting an object.
+    #[serde(default = "default_density")]
+    pub density: f64,
+    /// Height of the map, from `0` to `1`.
+    #[serde(default)]
+    pub height: Range,
+    /// In degrees.
+    #[serde(default)]
+    pub slope: Range,
+    /// Width of the soft edges of the slope range, in degrees: past its bounds, the share of the
+    /// positions getting an object fades out over this width instead of stopping at once.
+    #[serde(default)]
+    pub slope_falloff: f64,
+    /// Ignored when the map has no biomes.
+    #[serde(default)]
+    pub moisture: Range,
+    /// Names of the biomes, any of them when empty. Ignored when the map has no biomes.
+    #[serde(default)]
+    pub biomes: Vec<String>,
+    /// Scale of the objects, picked uniformly in the range.
+    #[serde(default = "default_scale")]
+    pub scale: Range,
+    #[serde(default)]
+    pub symbol: Symbol,
+    #[serde(default = "default_color")]
+    pub color: Color,
+    /// Radius of the symbol of an object of scale `1`, in pixels.
+    #[serde(default = "default_size")]
+    pub size: f64,
+}
+
+fn default_density() -> f64 {
+    1.0
+}
+
+fn default_scale() -> Range {
+    Range::new(1.0, 1.0)
+}
+
+fn default_color() -> Color {
+    Color::new([30, 60, 30])
+}
+
+fn default_size() -> f64 {
+    2.0
+}
@@ -0,0 +1,184 @@
+//! Objects scattered over a map by rules.
+use biome::{Biome, BiomeMap};
+use heightmap::{Heightmap, Sample};
+use poisson::poisson_disk;
+use rand::Rng;
+use rule::ScatterRule;
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::Error;
+use serde_json;
+use std::io;
+use std::io::Write;
+
+/// File format of the list of objects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectFormat {
+    Json,
+    Csv,
+}
+
+impl Default for ObjectFormat {
+    fn default() -> ObjectFormat {
+        ObjectFormat::Json
+    }
+}
+
+impl ObjectFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ObjectFormat::Json => "json",
+            ObjectFormat::Csv => "csv",
+        }
+    }
+}
+
+impl Serialize for ObjectFormat {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_str(self.extension())
+    }
+}
+
+impl Deserialize for ObjectFormat {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer
+    {
+        let string = String::deserialize(deserializer)?;
+        match string.as_str() {
+            "json" => Ok(ObjectFormat::Json),
+            "csv" => Ok(ObjectFormat::Csv),
+            s => Err(D::Error::unknown_variant(s, &["json", "csv"])),
+        }
+    }
+}
+
+/// Object placed on the map.
+#[derive(Clone, Debug, Serialize)]
+pub struct Placement {
+    /// Name of the rule.
+    pub name: String,
+    /// Position in samples from the top left corner of the map.
+    pub x: f64,
+    pub y: f64,
+    /// Height of the map under the object.
+    pub height: f64,
+    /// Around the vertical axis, in degrees.
+    pub rotation: f64,
+    pub scale: f64,
+}
+
+/// Rasters the rules are checked against, all of the size of the height map.
+pub struct Layers<'a, T: 'a> {
+    pub height: &'a Heightmap<T>,
+    /// In degrees.
+    pub slope: &'a Heightmap,
+    pub moisture: Option<&'a Heightmap>,
+    pub biomes: Option<(&'a BiomeMap, &'a [Biome])>,
+}
+
+impl<'a, T> Layers<'a, T>
+    where T: Sample
+{
+    /// Whether the rule allows objects at a sample, the slope being weighed by `place`.
+    fn allow(&self, rule: &ScatterRule, x: u32, y: u32) -> bool {
+        rule.height.contains(self.height.get(x, y)) &&
+        self.moisture.map_or(true, |m| rule.moisture.contains(m.get(x, y))) &&
+        (rule.biomes.is_empty() ||
+         self.biomes.map_or(true, |(map, biomes)| {
+            biomes
+                .get(map.id(x, y) as usize)
+                .map_or(false, |b| rule.biomes.iter().any(|name| *name == b.name))
+        }))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ScatterOptions {
+    /// Applied in turn, objects of a rule keeping away from the ones of the previous rules.
+    pub rules: Vec<ScatterRule>,
+    #[serde(default)]
+    pub export: ObjectFormat,
+    /// Whether symbols are drawn on the rendered map.
+    #[serde(default = "default_draw")]
+    pub draw: bool,
+}
+
+fn default_draw() -> bool {
+    true
+}
+
+impl ScatterOptions {
+    /// Objects of all the rules, at Poisson-disk distributed positions allowed by their rule.
+    pub fn place<T, R>(&self, layers: &Layers<T>, rng: &mut R) -> Vec<Placement>
+        where T: Sample,
+              R: Rng
+    {
+        let (width, height) = (layers.height.width(), layers.height.height());
+
+        // Objects are bucketed in cells as large as the largest spacing, neighbours being in the
+        // surrounding cells
+        let cell = self.rules.iter().fold(1.0f64, |c, r| c.max(r.spacing));
+        let (columns, rows) = ((f64::from(width) / cell).ceil() as usize + 1,
+                               (f64::from(height) / cell).ceil() as usize + 1);
+        let mut buckets: Vec<Vec<usize>> = vec![Vec::new(); columns * rows];
+
+        let mut objects: Vec<Placement> = Vec::new();
+        for rule in &self.rules {
+            // Share of the density kept at each sample
+            let slope = layers.slope.mask(rule.slope.min, rule.slope.max, rule.slope_falloff);
+            let points = poisson_disk(f64::from(width - 1), f64::from(height - 1), rule.spacing, rng);
+            for (px, py) in points {
+                let (x, y) = (px.round() as u32, py.round() as u32);
+                if !layers.allow(rule, x, y) || rng.gen::<f64>() >= rule.density * slope.get(x, y) {
+                    continue;
+                }
+
+                let (cx, cy) = ((px / cell) as usize, (py / cell) as usize);
+                let near = (cy.saturating_sub(1)..(cy + 2).min(rows))
+                    .flat_map(|j| (cx.saturating_sub(1)..(cx + 2).min(columns)).map(move |i| j * columns + i))
+                    .flat_map(|c| buckets[c].iter())
+                    .any(|&o| (objects[o].x - px).hypot(objects[o].y - py) < rule.spacing);
+                if near {
+                    continue;
+                }
+
+                buckets[cy * columns + cx].push(objects.len());
+                objects.push(Placement {
+                                 name: rule.name.clone(),
+                                 x: px,
+                                 y: py,
+                                 height: layers.height.get(x, y),
+                                 rotation: rng.gen::<f64>() * 360.0,
+                                 scale: rule.scale.min + (rule.scale.max - rule.scale.min) * rng.gen::<f64>(),
+                             });
+            }
+        }
+
+        objects
+    }
+
+    /// Writes the objects in the export format.
+    pub fn write_objects<W: Write>(&self, mut w: W, objects: &[Placement]) -> io::Result<()> {
+        match self.export {
+            ObjectFormat::Json => {
+                serde_json::to_writer_pretty(&mut w, objects).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+            }
+            ObjectFormat::Csv => {
+                writeln!(w, "name,x,y,height,rotation,scale")?;
+                for o in objects {
+                    writeln!(w,
+                             "\"{}\",{},{},{},{},{}",
+                             o.name.replace('"', "\"\""),
+                             o.x,
+                             o.y,
+                             o.height,
+                             o.rotation,
+                             o.scale)?;
+                }
+            }
+        }
+
+        w.flush()
+    }
+}
@@ -0,0 +1,16 @@
+extern crate heightmap;
+extern crate image;
+extern crate path;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+
+mod network;
+mod settlement;
+mod site;
+
+pub use network::{Network, Settlement};
+pub use settlement::{RoadCost, SettlementKind, SettlementOptions};
+pub use site::SiteScore;
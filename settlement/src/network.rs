@@ -0,0 +1,154 @@
+//! Settlements and the roads between them, drawn on a map or exported as GeoJSON.
+use image::{GeoReference, Rgb, RgbImage};
+use path::Point;
+use serde_json;
+use settlement::SettlementOptions;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::io::Write;
+
+#[derive(Clone, Debug)]
+pub struct Settlement {
+    /// Name of the kind of settlement.
+    pub kind: String,
+    pub x: u32,
+    pub y: u32,
+    /// Score of the site.
+    pub score: f64,
+}
+
+impl Settlement {
+    pub fn point(&self) -> Point {
+        (self.x, self.y)
+    }
+}
+
+pub struct Network {
+    pub settlements: Vec<Settlement>,
+    /// Samples of each road, roads ending at settlements and junctions.
+    pub roads: Vec<Vec<Point>>,
+}
+
+impl Network {
+    /// Merges the paths between settlements: the samples shared by several paths belong to a
+    /// single road, which splits where paths join or part.
+    pub fn new(settlements: Vec<Settlement>, paths: &[Vec<Point>]) -> Network {
+        let mut links: HashMap<Point, Vec<Point>> = HashMap::new();
+        let mut seen = HashSet::new();
+        for path in paths {
+            for step in path.windows(2) {
+                let (a, b) = (step[0].min(step[1]), step[0].max(step[1]));
+                if a != b && seen.insert((a, b)) {
+                    links.entry(a).or_insert_with(Vec::new).push(b);
+                    links.entry(b).or_insert_with(Vec::new).push(a);
+                }
+            }
+        }
+
+        let towns: HashSet<Point> = settlements.iter().map(|s| s.point()).collect();
+        let is_node = |p: &Point| towns.contains(p) || links[p].len() != 2;
+
+        // Roads go from node to node, loops without any node starting anywhere
+        let mut starts: Vec<Point> = links.keys().cloned().filter(|p| is_node(p)).collect();
+        starts.sort();
+        let mut rest: Vec<Point> = links.keys().cloned().collect();
+        rest.sort();
+        starts.extend(rest);
+
+        let mut walked = HashSet::new();
+        let mut roads = Vec::new();
+        for start in starts {
+            for &next in &links[&start] {
+                if walked.contains(&(start.min(next), start.max(next))) {
+                    continue;
+                }
+
+                let mut road = vec![start];
+                let (mut previous, mut current) = (start, next);
+                loop {
+                    walked.insert((previous.min(current), previous.max(current)));
+                    road.push(current);
+                    if is_node(&current) || current == start {
+                        break;
+                    }
+
+                    match links[&current].iter().find(|&&n| n != previous) {
+                        Some(&n) if !walked.contains(&(current.min(n), current.max(n))) => {
+                            previous = current;
+                            current = n;
+                        }
+                        _ => break,
+                    }
+                }
+                roads.push(road);
+            }
+        }
+
+        Network { settlements, roads }
+    }
+
+    /// Draws the roads, then the settlements as rings.
+    pub fn draw(&self, img: &mut RgbImage, options: &SettlementOptions) {
+        let (width, height) = img.dimensions();
+        for road in &self.roads {
+            for &(x, y) in road {
+                img.put_pixel(x, y, *options.road_color);
+            }
+        }
+
+        for s in &self.settlements {
+            let size = options.kinds.iter().find(|k| k.name == s.kind).map_or(2.0, |k| k.size);
+            let r = size.ceil() as i64;
+            for dy in -r..r + 1 {
+                for dx in -r..r + 1 {
+                    let (x, y) = (i64::from(s.x) + dx, i64::from(s.y) + dy);
+                    let d = (dx as f64).hypot(dy as f64);
+                    if x < 0 || y < 0 || x >= i64::from(width) || y >= i64::from(height) || d > size {
+                        continue;
+                    }
+
+                    // Light center on large marks
+                    let color = if size >= 2.0 && d <= size - 1.5 {
+                        Rgb([255, 255, 255])
+                    } else {
+                        *options.town_color
+                    };
+                    img.put_pixel(x as u32, y as u32, color);
+                }
+            }
+        }
+    }
+
+    /// Writes the settlements as points and the roads as lines, in the coordinates of `georef` for a
+    /// map `rows` samples high.
+    pub fn write_geojson<W: Write>(&self, mut w: W, georef: &GeoReference, rows: u32) -> io::Result<()> {
+        let position = |x: u32, y: u32| {
+            let (x, y) = georef.position(f64::from(x), f64::from(y), rows);
+            json!([x, y])
+        };
+
+        let mut features: Vec<_> = self.settlements
+            .iter()
+            .map(|s| {
+                json!({
+                    "type": "Feature",
+                    "geometry": {"type": "Point", "coordinates": position(s.x, s.y)},
+                    "properties": {"kind": s.kind, "score": s.score},
+                })
+            })
+            .collect();
+        features.extend(self.roads.iter().map(|road| {
+            let coordinates: Vec<_> = road.iter().map(|&(x, y)| position(x, y)).collect();
+            json!({
+                "type": "Feature",
+                "geometry": {"type": "LineString", "coordinates": coordinates},
+                "properties": {"kind": "road"},
+            })
+        }));
+
+        let collection = json!({"type": "FeatureCollection", "features": features});
+        serde_json::to_writer_pretty(&mut w, &collection).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+        w.flush()
+    }
+}
@@ -0,0 +1,255 @@
+//! Settlements placed on the best sites and linked by roads.
+use heightmap::{Heightmap, Sample};
+use image::Color;
+use network::{Network, Settlement};
+use path::{CostModel, Point, Terrain, astar};
+use site::SiteScore;
+use std::cmp::Ordering;
+use std::f64;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SettlementKind {
+    pub name: String,
+    /// Number of settlements of the kind, fewer when the map lacks room.
+    pub count: u32,
+    /// Smallest distance to any previous settlement, in samples.
+    pub spacing: f64,
+    /// Radius of the mark drawn on the map, in pixels.
+    #[serde(default = "default_size")]
+    pub size: f64,
+}
+
+fn default_size() -> f64 {
+    2.0
+}
+
+impl SettlementKind {
+    fn new(name: &str, count: u32, spacing: f64, size: f64) -> SettlementKind {
+        SettlementKind {
+            name: name.to_string(),
+            count,
+            spacing,
+            size,
+        }
+    }
+}
+
+/// Cost of a road over the terrain, `1` per distance on flat ground.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct RoadCost {
+    /// Walking costs of the roads, the cost of water being the one of bridges.
+    #[serde(default = "default_terrain")]
+    pub terrain: CostModel,
+    /// Factor of the cost along existing roads, lower than `1` to make roads share their segments.
+    #[serde(default = "default_reuse")]
+    pub reuse: f64,
+}
+
+fn default_terrain() -> CostModel {
+    CostModel {
+        water: Some(20.0),
+        uphill: 10.0,
+        ..CostModel::default()
+    }
+}
+
+fn default_reuse() -> f64 {
+    0.5
+}
+
+impl Default for RoadCost {
+    fn default() -> RoadCost {
+        RoadCost {
+            terrain: default_terrain(),
+            reuse: default_reuse(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SettlementOptions {
+    /// Placed in turn, the largest first.
+    #[serde(default = "default_kinds")]
+    pub kinds: Vec<SettlementKind>,
+    /// Share of the map draining through a sample from which it is a river.
+    #[serde(default = "default_river")]
+    pub river: f64,
+    #[serde(default)]
+    pub site: SiteScore,
+    #[serde(default)]
+    pub road: RoadCost,
+    #[serde(default = "default_town_color")]
+    pub town_color: Color,
+    #[serde(default = "default_road_color")]
+    pub road_color: Color,
+    /// Whether roads and settlements are drawn on the rendered map.
+    #[serde(default = "default_draw")]
+    pub draw: bool,
+}
+
+fn default_kinds() -> Vec<SettlementKind> {
+    vec![
+        SettlementKind::new("city", 2, 100.0, 4.0),
+        SettlementKind::new("town", 6, 50.0, 3.0),
+        SettlementKind::new("village", 16, 25.0, 2.0),
+    ]
+}
+
+fn default_river() -> f64 {
+    0.002
+}
+
+fn default_town_color() -> Color {
+    Color::new([40, 20, 20])
+}
+
+fn default_road_color() -> Color {
+    Color::new([140, 80, 40])
+}
+
+fn default_draw() -> bool {
+    true
+}
+
+impl Default for SettlementOptions {
+    fn default() -> SettlementOptions {
+        SettlementOptions {
+            kinds: default_kinds(),
+            river: default_river(),
+            site: SiteScore::default(),
+            road: RoadCost::default(),
+            town_color: default_town_color(),
+            road_color: default_road_color(),
+            draw: default_draw(),
+        }
+    }
+}
+
+impl SettlementOptions {
+    /// Score of each sample as a site, `slope` being in degrees and the sea below `sea_level`.
+    pub fn scores<T>(&self, hmap: &Heightmap<T>, slope: &Heightmap, sea_level: f64) -> Heightmap
+        where T: Sample
+    {
+        let sea: Vec<bool> = hmap.heights().map(|h| h.to_f64() < sea_level).collect();
+        let threshold = self.river * f64::from(hmap.width()) * f64::from(hmap.height());
+        let rivers: Vec<bool> = hmap.flow_accumulation()
+            .heights()
+            .zip(sea.iter())
+            .map(|(&f, &s)| !s && f >= threshold)
+            .collect();
+
+        self.site.scores(hmap, slope, &sea, &rivers)
+    }
+
+    /// Settlements on the best sites, linked by the roads of a minimum spanning tree. `scale` is the
+    /// height of a sample at `1.0`, in distances between two samples, and the sea lies below `sea_level`.
+    pub fn build<T>(&self, hmap: &Heightmap<T>, scale: f64, sea_level: f64) -> Network
+        where T: Sample
+    {
+        let scores = self.scores(hmap, &hmap.slope(scale), sea_level);
+        let width = hmap.width();
+        let mut sites: Vec<(u32, f64)> = scores.heights()
+            .enumerate()
+            .filter(|&(_, &s)| s > 0.0)
+            .map(|(i, &s)| (i as u32, s))
+            .collect();
+        sites.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+
+        let mut settlements: Vec<Settlement> = Vec::new();
+        for kind in &self.kinds {
+            let mut count = 0;
+            for &(i, score) in &sites {
+                if count >= kind.count {
+                    break;
+                }
+
+                let (x, y) = (i % width, i / width);
+                if settlements.iter().all(|s| distance((s.x, s.y), (x, y)) >= kind.spacing) {
+                    settlements.push(Settlement {
+                                         kind: kind.name.clone(),
+                                         x,
+                                         y,
+                                         score,
+                                     });
+                    count += 1;
+                }
+            }
+        }
+
+        let roads = self.roads(hmap, scale, sea_level, &settlements);
+        Network::new(settlements, &roads)
+    }
+
+    /// Paths of the roads, the shortest links first so that the longer ones follow them.
+    fn roads<T>(&self, hmap: &Heightmap<T>, scale: f64, sea_level: f64, settlements: &[Settlement]) -> Vec<Vec<Point>>
+        where T: Sample
+    {
+        let (width, height) = (hmap.width(), hmap.height());
+        let mut links = spanning_tree(settlements);
+        links.sort_by(|&(a, b), &(c, d)| {
+            let (ab, cd) = (distance(settlements[a].point(), settlements[b].point()),
+                            distance(settlements[c].point(), settlements[d].point()));
+            ab.partial_cmp(&cd).unwrap_or(Ordering::Equal)
+        });
+
+        let terrain = Terrain::new(hmap, scale, sea_level, &self.road.terrain);
+        let min_cost = self.road.reuse.min(1.0) * terrain.min_cost();
+        let mut on_road = vec![false; width as usize * height as usize];
+        let mut roads = Vec::new();
+        for (a, b) in links {
+            let path = {
+                let cost = |from: Point, (x, y): Point, length: f64| {
+                    let reuse = if on_road[(y * width + x) as usize] { self.road.reuse } else { 1.0 };
+                    terrain.cost(from, (x, y), length).map(|c| c * reuse)
+                };
+                astar(width, height, settlements[a].point(), settlements[b].point(), min_cost, cost)
+            };
+
+            if let Some(path) = path {
+                for &(x, y) in &path {
+                    on_road[(y * width + x) as usize] = true;
+                }
+                roads.push(path);
+            }
+        }
+
+        roads
+    }
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    (f64::from(a.0) - f64::from(b.0)).hypot(f64::from(a.1) - f64::from(b.1))
+}
+
+/// Links of the shortest tree connecting all the settlements (Prim).
+fn spanning_tree(settlements: &[Settlement]) -> Vec<(usize, usize)> {
+    let mut links = Vec::new();
+    if settlements.is_empty() {
+        return links;
+    }
+
+    let mut linked = vec![false; settlements.len()];
+    let mut nearest: Vec<(f64, usize)> = settlements
+        .iter()
+        .map(|s| (distance(s.point(), settlements[0].point()), 0))
+        .collect();
+    linked[0] = true;
+
+    for _ in 1..settlements.len() {
+        let next = (0..settlements.len())
+            .filter(|&i| !linked[i])
+            .min_by(|&i, &j| nearest[i].0.partial_cmp(&nearest[j].0).unwrap_or(Ordering::Equal))
+            .unwrap();
+        linked[next] = true;
+        links.push((nearest[next].1, next));
+
+        for i in 0..settlements.len() {
+            let d = distance(settlements[i].point(), settlements[next].point());
+            if !linked[i] && d < nearest[i].0 {
+                nearest[i] = (d, next);
+            }
+        }
+    }
+
+    links
+}
@@ -0,0 +1,118 @@
+//! Suitability of the samples of a map for a settlement.
+use heightmap::{Heightmap, Sample, heightmap_from_vec};
+use std::f64;
+
+/// Weights of the qualities of a site, summed into its score.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct SiteScore {
+    /// Weight of flat ground, from `1` on flat ground to `0` at `max_slope`.
+    #[serde(default = "default_weight")]
+    pub flat: f64,
+    /// Weight of the proximity of the sea.
+    #[serde(default = "default_weight")]
+    pub coast: f64,
+    /// Weight of the proximity of a river.
+    #[serde(default = "default_weight")]
+    pub river: f64,
+    /// Distance in samples at which the proximity of water weighs a third of its weight.
+    #[serde(default = "default_reach")]
+    pub reach: f64,
+    /// Steepest slope of a site, in degrees.
+    #[serde(default = "default_max_slope")]
+    pub max_slope: f64,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+fn default_reach() -> f64 {
+    10.0
+}
+
+fn default_max_slope() -> f64 {
+    15.0
+}
+
+impl Default for SiteScore {
+    fn default() -> SiteScore {
+        SiteScore {
+            flat: default_weight(),
+            coast: default_weight(),
+            river: default_weight(),
+            reach: default_reach(),
+            max_slope: default_max_slope(),
+        }
+    }
+}
+
+impl SiteScore {
+    /// Score of each sample, `0` under the sea level or on steep ground. `slope` is in degrees.
+    pub fn scores<T>(&self, hmap: &Heightmap<T>, slope: &Heightmap, sea: &[bool], rivers: &[bool]) -> Heightmap
+        where T: Sample
+    {
+        let (width, height) = (hmap.width(), hmap.height());
+        let to_coast = distances(sea, width, height);
+        let to_river = distances(rivers, width, height);
+        let proximity = |d: f64| if self.reach > 0.0 { (-d / self.reach).exp() } else { 0.0 };
+
+        let scores = (0..(width * height) as usize)
+            .map(|i| {
+                let s = slope.get(i as u32 % width, i as u32 / width);
+                if sea[i] || s >= self.max_slope {
+                    0.0
+                } else {
+                    self.flat * (1.0 - s / self.max_slope) + self.coast * proximity(to_coast[i]) +
+                    self.river * proximity(to_river[i])
+                }
+            })
+            .collect();
+
+        heightmap_from_vec(width, height, scores)
+    }
+}
+
+/// Distance in samples from each sample to the nearest marked one, infinite without any: two
+/// passes propagating the distances of the neighbours (chamfer).
+fn distances(marks: &[bool], width: u32, height: u32) -> Vec<f64> {
+    let (width, height) = (width as usize, height as usize);
+    let mut d: Vec<f64> = marks.iter().map(|&m| if m { 0.0 } else { f64::INFINITY }).collect();
+    let diagonal = f64::consts::SQRT_2;
+
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            if x > 0 {
+                d[i] = d[i].min(d[i - 1] + 1.0);
+            }
+            if y > 0 {
+                d[i] = d[i].min(d[i - width] + 1.0);
+                if x > 0 {
+                    d[i] = d[i].min(d[i - width - 1] + diagonal);
+                }
+                if x + 1 < width {
+                    d[i] = d[i].min(d[i - width + 1] + diagonal);
+                }
+            }
+        }
+    }
+    for y in (0..height).rev() {
+        for x in (0..width).rev() {
+            let i = y * width + x;
+            if x + 1 < width {
+                d[i] = d[i].min(d[i + 1] + 1.0);
+            }
+            if y + 1 < height {
+                d[i] = d[i].min(d[i + width] + 1.0);
+                if x + 1 < width {
+                    d[i] = d[i].min(d[i + width + 1] + diagonal);
+                }
+                if x > 0 {
+                    d[i] = d[i].min(d[i + width - 1] + diagonal);
+                }
+            }
+        }
+    }
+
+    d
+}
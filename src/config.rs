@@ -1,5 +1,12 @@
-use image::{Color, ColorRamp, Vec3};
+use biome::BiomeOptions;
+use contour::{ContourOptions, LandmassOptions, VectorOptions};
+use image::{Color, ColorRamp, FantasyOptions, GeoReference, IsometricOptions, NormalMapOptions, PreviewOptions,
+            ShadeOptions, Vec3};
+use mesh::{LodOptions, MeshOptions};
+use path::CostModel;
 use rand::{Rng, StdRng};
+use scatter::ScatterOptions;
+use settlement::SettlementOptions;
 use serde::{de, ser};
 use serde_yaml;
 use std::convert::From;
@@ -172,6 +179,285 @@ impl ser::Serialize for Noise {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Storage {
+    F64,
+    F32,
+    U16,
+}
+
+impl Storage {
+    const VARIANTS: &'static [&'static str] = &["f64", "f32", "u16"];
+}
+
+impl FromStr for Storage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "f64" => Ok(Storage::F64),
+            "f32" => Ok(Storage::F32),
+            "u16" => Ok(Storage::U16),
+            s => Err(format!("Cannot convert {} to Storage", s)),
+        }
+    }
+}
+
+impl From<Storage> for &'static str {
+    fn from(storage: Storage) -> Self {
+        match storage {
+            Storage::F64 => Storage::VARIANTS[0],
+            Storage::F32 => Storage::VARIANTS[1],
+            Storage::U16 => Storage::VARIANTS[2],
+        }
+    }
+}
+
+impl de::Deserialize for Storage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer,
+    {
+        let s = String::deserialize(deserializer)?;
+        Self::from_str(&s).map_err(|_| de::Error::unknown_variant(&s, Storage::VARIANTS))
+    }
+}
+
+impl ser::Serialize for Storage {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_str(From::from(*self))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Png,
+    Png16,
+    R16,
+    R16be,
+    F32,
+    Asc,
+    GeoTiff,
+    Obj,
+    Stl,
+    Gltf,
+    Glb,
+    NormalMap,
+    Svg,
+    Preview,
+    Isometric,
+}
+
+impl Format {
+    const VARIANTS: &'static [&'static str] = &[
+        "png",
+        "png16",
+        "r16",
+        "r16be",
+        "f32",
+        "asc",
+        "tif",
+        "obj",
+        "stl",
+        "gltf",
+        "glb",
+        "normal",
+        "svg",
+        "preview",
+        "isometric",
+    ];
+
+    /// Guess the format from the extension of the output file, defaults to the coloured render.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Format {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("r16") | Some("raw") => Format::R16,
+            Some("f32") => Format::F32,
+            Some("asc") => Format::Asc,
+            Some("tif") | Some("tiff") => Format::GeoTiff,
+            Some("obj") => Format::Obj,
+            Some("stl") => Format::Stl,
+            Some("gltf") => Format::Gltf,
+            Some("glb") => Format::Glb,
+            Some("svg") => Format::Svg,
+            _ => Format::Png,
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "png" => Ok(Format::Png),
+            "png16" => Ok(Format::Png16),
+            "r16" => Ok(Format::R16),
+            "r16be" => Ok(Format::R16be),
+            "f32" => Ok(Format::F32),
+            "asc" => Ok(Format::Asc),
+            "tif" => Ok(Format::GeoTiff),
+            "obj" => Ok(Format::Obj),
+            "stl" => Ok(Format::Stl),
+            "gltf" => Ok(Format::Gltf),
+            "glb" => Ok(Format::Glb),
+            "normal" => Ok(Format::NormalMap),
+            "svg" => Ok(Format::Svg),
+            "preview" => Ok(Format::Preview),
+            "isometric" => Ok(Format::Isometric),
+            s => Err(format!("Cannot convert {} to Format", s)),
+        }
+    }
+}
+
+impl From<Format> for &'static str {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Png => Format::VARIANTS[0],
+            Format::Png16 => Format::VARIANTS[1],
+            Format::R16 => Format::VARIANTS[2],
+            Format::R16be => Format::VARIANTS[3],
+            Format::F32 => Format::VARIANTS[4],
+            Format::Asc => Format::VARIANTS[5],
+            Format::GeoTiff => Format::VARIANTS[6],
+            Format::Obj => Format::VARIANTS[7],
+            Format::Stl => Format::VARIANTS[8],
+            Format::Gltf => Format::VARIANTS[9],
+            Format::Glb => Format::VARIANTS[10],
+            Format::NormalMap => Format::VARIANTS[11],
+            Format::Svg => Format::VARIANTS[12],
+            Format::Preview => Format::VARIANTS[13],
+            Format::Isometric => Format::VARIANTS[14],
+        }
+    }
+}
+
+impl de::Deserialize for Format {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer,
+    {
+        let s = String::deserialize(deserializer)?;
+        Self::from_str(&s).map_err(|_| de::Error::unknown_variant(&s, Format::VARIANTS))
+    }
+}
+
+impl ser::Serialize for Format {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_str(From::from(*self))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Analysis {
+    Slope,
+    Aspect,
+    ProfileCurvature,
+    PlanCurvature,
+    Ruggedness,
+}
+
+impl Analysis {
+    const VARIANTS: &'static [&'static str] = &["slope", "aspect", "profile-curvature", "plan-curvature", "ruggedness"];
+}
+
+impl FromStr for Analysis {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "slope" => Ok(Analysis::Slope),
+            "aspect" => Ok(Analysis::Aspect),
+            "profile-curvature" => Ok(Analysis::ProfileCurvature),
+            "plan-curvature" => Ok(Analysis::PlanCurvature),
+            "ruggedness" => Ok(Analysis::Ruggedness),
+            s => Err(format!("Cannot convert {} to Analysis", s)),
+        }
+    }
+}
+
+impl From<Analysis> for &'static str {
+    fn from(analysis: Analysis) -> Self {
+        match analysis {
+            Analysis::Slope => Analysis::VARIANTS[0],
+            Analysis::Aspect => Analysis::VARIANTS[1],
+            Analysis::ProfileCurvature => Analysis::VARIANTS[2],
+            Analysis::PlanCurvature => Analysis::VARIANTS[3],
+            Analysis::Ruggedness => Analysis::VARIANTS[4],
+        }
+    }
+}
+
+impl de::Deserialize for Analysis {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer,
+    {
+        let s = String::deserialize(deserializer)?;
+        Self::from_str(&s).map_err(|_| de::Error::unknown_variant(&s, Analysis::VARIANTS))
+    }
+}
+
+impl ser::Serialize for Analysis {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_str(From::from(*self))
+    }
+}
+
+/// Cheapest path drawn over the map.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Route {
+    /// Column and row of the start.
+    pub from: (u32, u32),
+    pub to: (u32, u32),
+    #[serde(default)]
+    pub cost: CostModel,
+    #[serde(default = "default_route_color")]
+    pub color: Color,
+    /// Whether the cost of reaching each sample from the start is written next to the output.
+    #[serde(default)]
+    pub distances: bool,
+}
+
+pub fn default_route_color() -> Color {
+    Color::new([0xFFu8, 0x00u8, 0x00u8])
+}
+
+/// Samples seen by observers, tinted on the map.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Viewshed {
+    /// Column and row of each observer.
+    pub observers: Vec<(u32, u32)>,
+    /// Height of the eyes of the observers above the ground.
+    #[serde(default = "default_observer_height")]
+    pub height: f64,
+    #[serde(default = "default_viewshed_color")]
+    pub color: Color,
+    /// Opacity of the tint where all the observers see.
+    #[serde(default = "default_viewshed_opacity")]
+    pub opacity: f64,
+}
+
+pub fn default_observer_height() -> f64 {
+    0.01
+}
+
+pub fn default_viewshed_color() -> Color {
+    Color::new([0xFFu8, 0x99u8, 0x00u8])
+}
+
+pub fn default_viewshed_opacity() -> f64 {
+    0.5
+}
+
 #[derive(Builder, Debug, Deserialize, Serialize)]
 pub struct MapGeneratorConfig {
     #[serde(default = "default_width")]
@@ -185,6 +471,14 @@ pub struct MapGeneratorConfig {
     lacunarity: Option<f64>,
     persistance: Option<f64>,
     interpolation: Option<Interpolation>,
+    input: Option<String>,
+    input_format: Option<Format>,
+    #[serde(default = "default_storage")]
+    storage: Storage,
+    mmap: Option<String>,
+    tile_size: Option<u32>,
+    #[serde(default = "default_sea_level")]
+    sea_level: f64,
     #[serde(default = "default_ramp")]
     ramp: ColorRamp,
     #[serde(default = "default_light_position")]
@@ -193,10 +487,36 @@ pub struct MapGeneratorConfig {
     light: Color,
     #[serde(default = "default_dark")]
     dark: Color,
+    shading: Option<ShadeOptions>,
+    fantasy: Option<FantasyOptions>,
+    analysis: Option<Analysis>,
+    #[serde(default = "default_analysis_ramp")]
+    analysis_ramp: ColorRamp,
+    biome: Option<BiomeOptions>,
+    scatter: Option<ScatterOptions>,
+    settlement: Option<SettlementOptions>,
+    route: Option<Route>,
+    viewshed: Option<Viewshed>,
+    contour: Option<ContourOptions>,
+    landmass: Option<LandmassOptions>,
     #[serde(default = "default_output")]
     #[serde(skip_serializing)]
     #[serde(skip_deserializing)]
     output: String,
+    format: Option<Format>,
+    #[serde(default)]
+    georeference: GeoReference,
+    #[serde(default)]
+    mesh: MeshOptions,
+    lod: Option<LodOptions>,
+    #[serde(default)]
+    normal_map: NormalMapOptions,
+    #[serde(default)]
+    vector: VectorOptions,
+    #[serde(default)]
+    preview: PreviewOptions,
+    #[serde(default)]
+    isometric: IsometricOptions,
     #[serde(default = "default_seed")]
     seed: usize,
 }
@@ -217,6 +537,14 @@ pub fn default_seed() -> usize {
     StdRng::new().unwrap().gen()
 }
 
+pub fn default_storage() -> Storage {
+    Storage::F64
+}
+
+pub fn default_sea_level() -> f64 {
+    0.5
+}
+
 pub fn default_light_position() -> Vec3 {
     Vec3::new(-1.0, -1.0, 0.0)
 }
@@ -245,6 +573,16 @@ pub fn default_ramp() -> ColorRamp {
     ramp
 }
 
+pub fn default_analysis_ramp() -> ColorRamp {
+    let mut ramp = ColorRamp::new();
+
+    ramp.add_step(0.000, Color::new([49u8, 54u8, 149u8])); // blue: low
+    ramp.add_step(0.500, Color::new([255u8, 255u8, 191u8])); // pale yellow: middle
+    ramp.add_step(1.000, Color::new([165u8, 0u8, 38u8])); // red: high
+
+    ramp
+}
+
 impl MapGeneratorConfig {
     pub fn read<P: AsRef<Path>>(path: P) -> io::Result<MapGeneratorConfig> {
         let mut file = File::open(path)?;
@@ -299,6 +637,72 @@ impl MapGeneratorConfig {
         &self.interpolation
     }
 
+    pub fn input(&self) -> &Option<String> {
+        &self.input
+    }
+
+    pub fn set_input(mut self, input: Option<String>) -> Self {
+        if input.is_some() {
+            self.input = input;
+        }
+
+        self
+    }
+
+    /// Format of the input, guessed from its extension when not set.
+    pub fn input_format(&self) -> Option<Format> {
+        self.input_format.or_else(|| self.input.as_ref().map(Format::from_path))
+    }
+
+    pub fn set_input_format(mut self, input_format: Option<Format>) -> Self {
+        if input_format.is_some() {
+            self.input_format = input_format;
+        }
+
+        self
+    }
+
+    pub fn storage(&self) -> Storage {
+        self.storage
+    }
+
+    pub fn set_storage(mut self, storage: Option<Storage>) -> Self {
+        if let Some(s) = storage {
+            self.storage = s;
+        }
+
+        self
+    }
+
+    pub fn mmap(&self) -> &Option<String> {
+        &self.mmap
+    }
+
+    pub fn set_mmap(mut self, mmap: Option<String>) -> Self {
+        if mmap.is_some() {
+            self.mmap = mmap;
+        }
+
+        self
+    }
+
+    pub fn tile_size(&self) -> &Option<u32> {
+        &self.tile_size
+    }
+
+    pub fn set_tile_size(mut self, tile_size: Option<u32>) -> Self {
+        if tile_size.is_some() {
+            self.tile_size = tile_size;
+        }
+
+        self
+    }
+
+    /// Height of the shore, the samples below it being under water in every output.
+    pub fn sea_level(&self) -> f64 {
+        self.sea_level
+    }
+
     pub fn light_position(&self) -> &Vec3 {
         &self.light_position
     }
@@ -311,6 +715,17 @@ impl MapGeneratorConfig {
         self.dark
     }
 
+    /// Hillshade with cast shadows and ambient occlusion, replacing the shading from
+    /// `light_position` when set.
+    pub fn shading(&self) -> Option<&ShadeOptions> {
+        self.shading.as_ref()
+    }
+
+    /// Map drawn on parchment, replacing the color ramp and the shading of PNG outputs when set.
+    pub fn fantasy(&self) -> Option<&FantasyOptions> {
+        self.fantasy.as_ref()
+    }
+
     pub fn output(&self) -> &String {
         &self.output
     }
@@ -323,6 +738,51 @@ impl MapGeneratorConfig {
         self
     }
 
+    /// Format of the output, guessed from its extension when not set.
+    pub fn format(&self) -> Format {
+        self.format.unwrap_or_else(|| Format::from_path(&self.output))
+    }
+
+    pub fn set_format(mut self, format: Option<Format>) -> Self {
+        if format.is_some() {
+            self.format = format;
+        }
+
+        self
+    }
+
+    pub fn georeference(&self) -> &GeoReference {
+        &self.georeference
+    }
+
+    pub fn mesh(&self) -> &MeshOptions {
+        &self.mesh
+    }
+
+    pub fn normal_map(&self) -> &NormalMapOptions {
+        &self.normal_map
+    }
+
+    /// Shapes of the SVG map.
+    pub fn vector(&self) -> &VectorOptions {
+        &self.vector
+    }
+
+    /// Camera of the perspective preview.
+    pub fn preview(&self) -> &PreviewOptions {
+        &self.preview
+    }
+
+    /// Tiles and layers of the isometric render.
+    pub fn isometric(&self) -> &IsometricOptions {
+        &self.isometric
+    }
+
+    /// Levels of detail of mesh outputs, written as a pyramid of tiles when set.
+    pub fn lod(&self) -> Option<&LodOptions> {
+        self.lod.as_ref()
+    }
+
     pub fn seed(&self) -> &usize {
         &self.seed
     }
@@ -338,4 +798,104 @@ impl MapGeneratorConfig {
     pub fn ramp(&self) -> &ColorRamp {
         &self.ramp
     }
+
+    /// Terrain analysis rendered instead of the height map.
+    pub fn analysis(&self) -> Option<Analysis> {
+        self.analysis
+    }
+
+    pub fn set_analysis(mut self, analysis: Option<Analysis>) -> Self {
+        if analysis.is_some() {
+            self.analysis = analysis;
+        }
+
+        self
+    }
+
+    pub fn analysis_ramp(&self) -> &ColorRamp {
+        &self.analysis_ramp
+    }
+
+    /// Biomes coloring the map instead of the ramp when set.
+    pub fn biome(&self) -> Option<&BiomeOptions> {
+        self.biome.as_ref()
+    }
+
+    /// Objects placed on the map when set.
+    pub fn scatter(&self) -> Option<&ScatterOptions> {
+        self.scatter.as_ref()
+    }
+
+    /// Settlements and roads placed on the map when set.
+    pub fn settlement(&self) -> Option<&SettlementOptions> {
+        self.settlement.as_ref()
+    }
+
+    /// Path rendered instead of the map when set.
+    pub fn route(&self) -> Option<&Route> {
+        self.route.as_ref()
+    }
+
+    pub fn set_route(mut self, route: Option<Route>) -> Self {
+        if route.is_some() {
+            self.route = route;
+        }
+
+        self
+    }
+
+    /// Samples seen by observers, tinted on the map when set.
+    pub fn viewshed(&self) -> Option<&Viewshed> {
+        self.viewshed.as_ref()
+    }
+
+    pub fn set_viewshed(mut self, viewshed: Option<Viewshed>) -> Self {
+        if viewshed.is_some() {
+            self.viewshed = viewshed;
+        }
+
+        self
+    }
+
+    /// Topographic contours drawn on the map when set.
+    pub fn contour(&self) -> Option<&ContourOptions> {
+        self.contour.as_ref()
+    }
+
+    /// Islands and seas counted, exported or constraining the seed when set.
+    pub fn landmass(&self) -> Option<&LandmassOptions> {
+        self.landmass.as_ref()
+    }
+
+    /// Keeps the other landmass options of the config file if any.
+    pub fn set_min_island_area(mut self, area: Option<f64>) -> Self {
+        if let Some(area) = area {
+            let landmass = self.landmass.take().unwrap_or_default();
+            self.landmass = Some(LandmassOptions {
+                min_island_area: Some(area),
+                ..landmass
+            });
+        }
+
+        self
+    }
+
+    /// Keeps the other contour options of the config file if any.
+    pub fn set_contour_interval(mut self, interval: Option<f64>) -> Self {
+        if let Some(interval) = interval {
+            let contour = self.contour.take().unwrap_or_default();
+            self.contour = Some(ContourOptions { interval, ..contour });
+        }
+
+        self
+    }
+
+    /// Keeps the biomes of the config file if any.
+    pub fn set_biome(mut self, biome: Option<BiomeOptions>) -> Self {
+        if self.biome.is_none() {
+            self.biome = biome;
+        }
+
+        self
+    }
 }
@@ -7,18 +7,30 @@ extern crate serde;
 extern crate serde_derive;
 extern crate serde_yaml;
 
+extern crate biome;
+extern crate contour;
 extern crate heightmap;
 extern crate image;
+extern crate mesh;
 extern crate noise2d;
+extern crate path;
+extern crate scatter;
+extern crate settlement;
 
 mod config;
 mod interpolate;
 mod map_generator;
 
-use clap::{App, Arg, ArgGroup};
+use biome::BiomeOptions;
+use clap::{App, Arg, ArgGroup, SubCommand};
+use contour::{ContourOptions, LandmassOptions, VectorOptions};
+use image::{GeoReference, IsometricOptions, NormalMapOptions, PreviewOptions};
+use mesh::MeshOptions;
+use path::CostModel;
 use rand::{Rng, StdRng};
 use std::error::Error;
 use std::path::Path;
+use std::process;
 use std::str::FromStr;
 
 fn main() {
@@ -79,6 +91,95 @@ fn main() {
                 .possible_values(&["linear", "cubic", "quintic", "cosine"])
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("input")
+                .long("input")
+                .help("Height map to render instead of generating one")
+                .takes_value(true)
+                .value_name("FILE")
+                .conflicts_with("mmap")
+                .validator(|i| {
+                    if Path::new(&i).is_file() {
+                        Ok(())
+                    } else {
+                        Err(format!("{} is not a valid filename", i))
+                    }
+                }),
+        )
+        .arg(
+            Arg::with_name("input-format")
+                .long("input-format")
+                .help("Input format, guessed from the input extension by default")
+                .possible_values(&["png", "png16", "r16", "r16be", "f32"])
+                .requires("input")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("storage")
+                .long("storage")
+                .help("Sample type used to store the height map")
+                .possible_values(&["f64", "f32", "u16"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("mmap")
+                .long("mmap")
+                .help("Keep the height map in a memory-mapped file and render it tile by tile")
+                .takes_value(true)
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::with_name("tile-size")
+                .long("tile-size")
+                .help("Size of the tiles of a memory-mapped height map")
+                .takes_value(true)
+                .requires("mmap")
+                .validator(|s| u32::from_str(&s).map(|_| ()).map_err(|e| e.description().to_string())),
+        )
+        .arg(
+            Arg::with_name("analysis")
+                .long("analysis")
+                .help("Render a terrain analysis instead of the height map")
+                .possible_values(&["slope", "aspect", "profile-curvature", "plan-curvature", "ruggedness"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("biomes")
+                .long("biomes")
+                .help("Color the map by biome, and write the biome of each pixel next to the output")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("observer")
+                .long("observer")
+                .help("Tint the samples seen from this position, may be repeated")
+                .value_name("X,Y")
+                .multiple(true)
+                .number_of_values(1)
+                .validator(|p| point(&p).map(|_| ())),
+        )
+        .arg(
+            Arg::with_name("observer-height")
+                .long("observer-height")
+                .help("Height of the eyes of the observers above the ground")
+                .takes_value(true)
+                .requires("observer")
+                .validator(|s| f64::from_str(&s).map(|_| ()).map_err(|e| e.description().to_string())),
+        )
+        .arg(
+            Arg::with_name("contours")
+                .long("contours")
+                .help("Draw contours every this height")
+                .value_name("INTERVAL")
+                .validator(|s| f64::from_str(&s).map(|_| ()).map_err(|e| e.description().to_string())),
+        )
+        .arg(
+            Arg::with_name("min-island-area")
+                .long("min-island-area")
+                .help("Try the next seeds until an island covers this share of the map")
+                .value_name("SHARE")
+                .validator(|s| f64::from_str(&s).map(|_| ()).map_err(|e| e.description().to_string())),
+        )
         .arg(
             Arg::with_name("output")
                 .short("o")
@@ -86,6 +187,17 @@ fn main() {
                 .help("generated file name")
                 .value_name("FILE"),
         )
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .help("Output format, guessed from the output extension by default")
+                .possible_values(&[
+                    "png", "png16", "r16", "r16be", "f32", "asc", "tif", "obj", "stl", "gltf", "glb", "normal", "svg",
+                    "preview", "isometric",
+                ])
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("write_config")
                 .long("write-config")
@@ -93,6 +205,53 @@ fn main() {
                 .hidden(true)
                 .help("Write config file. It will be generated in the same folder as the output"),
         )
+        .subcommand(
+            SubCommand::with_name("path")
+                .about("Render the cheapest path between two samples over the map")
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .help("Column and row of the start")
+                        .value_name("X,Y")
+                        .required(true)
+                        .validator(|p| point(&p).map(|_| ())),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .help("Column and row of the goal")
+                        .value_name("X,Y")
+                        .required(true)
+                        .validator(|p| point(&p).map(|_| ())),
+                )
+                .arg(
+                    Arg::with_name("max-slope")
+                        .long("max-slope")
+                        .help("Steepest move allowed, in degrees")
+                        .takes_value(true)
+                        .validator(|s| f64::from_str(&s).map(|_| ()).map_err(|e| e.description().to_string())),
+                )
+                .arg(
+                    Arg::with_name("uphill")
+                        .long("uphill")
+                        .help("Extra cost per distance climbed")
+                        .takes_value(true)
+                        .validator(|s| cost(&s).map(|_| ())),
+                )
+                .arg(
+                    Arg::with_name("water")
+                        .long("water")
+                        .help("Cost of a move into water per distance, water being impassable by default")
+                        .takes_value(true)
+                        .validator(|s| cost(&s).map(|_| ())),
+                )
+                .arg(
+                    Arg::with_name("distances")
+                        .long("distances")
+                        .help("Write the cost of reaching each sample from the start next to the output")
+                        .takes_value(false),
+                ),
+        )
         .group(
             ArgGroup::with_name("manual_group")
                 .args(&["generator", "noise", "interpolation"])
@@ -102,6 +261,43 @@ fn main() {
         .get_matches();
 
     let output = matches.value_of("output");
+    let storage = matches.value_of("storage").map(|s| FromStr::from_str(s).unwrap());
+    let format = matches.value_of("format").map(|f| FromStr::from_str(f).unwrap());
+    let input = matches.value_of("input").map(|i| i.to_string());
+    let input_format = matches.value_of("input-format").map(|f| FromStr::from_str(f).unwrap());
+    let mmap = matches.value_of("mmap").map(|m| m.to_string());
+    let tile_size = matches.value_of("tile-size").map(|t| u32::from_str(t).unwrap());
+    let analysis = matches.value_of("analysis").map(|a| FromStr::from_str(a).unwrap());
+    let biome = if matches.is_present("biomes") {
+        Some(BiomeOptions::default())
+    } else {
+        None
+    };
+    let viewshed = matches.values_of("observer").map(|observers| config::Viewshed {
+        observers: observers.map(|p| point(p).unwrap()).collect(),
+        height: matches
+            .value_of("observer-height")
+            .map_or_else(config::default_observer_height, |h| f64::from_str(h).unwrap()),
+        color: config::default_viewshed_color(),
+        opacity: config::default_viewshed_opacity(),
+    });
+    let contour_interval = matches.value_of("contours").map(|s| f64::from_str(s).unwrap());
+    let min_island_area = matches.value_of("min-island-area").map(|s| f64::from_str(s).unwrap());
+    let route = matches.subcommand_matches("path").map(|m| {
+        let default = CostModel::default();
+        config::Route {
+            from: point(m.value_of("from").unwrap()).unwrap(),
+            to: point(m.value_of("to").unwrap()).unwrap(),
+            cost: CostModel {
+                max_slope: m.value_of("max-slope").map(|s| f64::from_str(s).unwrap()),
+                uphill: m.value_of("uphill").map_or(default.uphill, |s| cost(s).unwrap()),
+                water: m.value_of("water").map(|s| cost(s).unwrap()),
+                ..default
+            },
+            color: config::default_route_color(),
+            distances: m.is_present("distances"),
+        }
+    });
     let seed = if matches.is_present("random-seed") {
         Some(StdRng::new().unwrap().gen())
     } else {
@@ -112,7 +308,19 @@ fn main() {
         config::MapGeneratorConfig::read(matches.value_of("config").unwrap())
             .expect("Could not read config file")
             .set_output(output.map(|o| o.to_string()))
+            .set_format(format)
             .set_seed(seed)
+            .set_input(input)
+            .set_input_format(input_format)
+            .set_storage(storage)
+            .set_mmap(mmap)
+            .set_tile_size(tile_size)
+            .set_analysis(analysis)
+            .set_biome(biome)
+            .set_route(route)
+            .set_viewshed(viewshed)
+            .set_contour_interval(contour_interval)
+            .set_min_island_area(min_island_area)
     } else if matches.value_of("generator").map_or(true, |g| g == "fractal") {
         config::MapGeneratorConfigBuilder::default()
             .width(config::default_width())
@@ -134,10 +342,41 @@ fn main() {
                     .map(|s| FromStr::from_str(s).unwrap())
                     .unwrap_or(config::Interpolation::Cubic),
             ))
+            .input(input)
+            .input_format(input_format)
+            .storage(storage.unwrap_or_else(config::default_storage))
+            .mmap(mmap)
+            .tile_size(tile_size)
+            .sea_level(config::default_sea_level())
             .light_position(config::default_light_position())
             .light(config::default_light())
             .dark(config::default_dark())
+            .shading(None)
+            .fantasy(None)
+            .analysis(analysis)
+            .analysis_ramp(config::default_analysis_ramp())
+            .biome(biome.clone())
+            .scatter(None)
+            .settlement(None)
+            .route(route.clone())
+            .viewshed(viewshed.clone())
+            .contour(contour_interval.map(|interval| ContourOptions {
+                interval,
+                ..ContourOptions::default()
+            }))
+            .landmass(min_island_area.map(|area| LandmassOptions {
+                min_island_area: Some(area),
+                ..LandmassOptions::default()
+            }))
             .output(output.map_or("out.png".to_string(), |filename| filename.to_string()))
+            .format(format)
+            .georeference(GeoReference::default())
+            .mesh(MeshOptions::default())
+            .lod(None)
+            .normal_map(NormalMapOptions::default())
+            .vector(VectorOptions::default())
+            .preview(PreviewOptions::default())
+            .isometric(IsometricOptions::default())
             .seed(seed.unwrap_or_else(config::default_seed))
             .ramp(config::default_ramp())
             .build()
@@ -158,10 +397,41 @@ fn main() {
             .lacunarity(None)
             .persistance(None)
             .interpolation(None)
+            .input(input)
+            .input_format(input_format)
+            .storage(storage.unwrap_or_else(config::default_storage))
+            .mmap(mmap)
+            .tile_size(tile_size)
+            .sea_level(config::default_sea_level())
             .light_position(config::default_light_position())
             .light(config::default_light())
             .dark(config::default_dark())
+            .shading(None)
+            .fantasy(None)
+            .analysis(analysis)
+            .analysis_ramp(config::default_analysis_ramp())
+            .biome(biome.clone())
+            .scatter(None)
+            .settlement(None)
+            .route(route.clone())
+            .viewshed(viewshed.clone())
+            .contour(contour_interval.map(|interval| ContourOptions {
+                interval,
+                ..ContourOptions::default()
+            }))
+            .landmass(min_island_area.map(|area| LandmassOptions {
+                min_island_area: Some(area),
+                ..LandmassOptions::default()
+            }))
             .output(output.map_or("out.png".to_string(), |filename| filename.to_string()))
+            .format(format)
+            .georeference(GeoReference::default())
+            .mesh(MeshOptions::default())
+            .lod(None)
+            .normal_map(NormalMapOptions::default())
+            .vector(VectorOptions::default())
+            .preview(PreviewOptions::default())
+            .isometric(IsometricOptions::default())
             .seed(seed.unwrap_or_else(config::default_seed))
             .ramp(config::default_ramp())
             .build()
@@ -173,5 +443,25 @@ fn main() {
         let _ = config.write(&config_name);
     }
     println!("Seed used: {}", config.seed());
-    map_generator::MapGenerator::new(config).run();
+    if let Err(e) = map_generator::MapGenerator::new(config).run() {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+/// Parses a sample position written `x,y`.
+fn point(s: &str) -> Result<(u32, u32), String> {
+    let mut iter = s.split(',').map(|c| u32::from_str(c.trim()));
+    match (iter.next(), iter.next(), iter.next()) {
+        (Some(Ok(x)), Some(Ok(y)), None) => Ok((x, y)),
+        _ => Err(format!("{} is not a valid position", s)),
+    }
+}
+
+fn cost(s: &str) -> Result<f64, String> {
+    match f64::from_str(s) {
+        Ok(c) if c >= 0.0 => Ok(c),
+        Ok(_) => Err(format!("{} is not a valid cost", s)),
+        Err(e) => Err(e.description().to_string()),
+    }
 }
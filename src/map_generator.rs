@@ -1,43 +1,114 @@
 use rand::{Rng, SeedableRng, StdRng};
-use std::path::Path;
+use std::cmp::{max, min};
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
 
-use config::{Generator, Interpolation, MapGeneratorConfig, Noise};
-use heightmap::{Diamond2d, Fractal2d, Generator2d, Midpoint2d};
-use image::Shadable;
+use biome::{BiomeMap, BiomeOptions};
+use config::{Analysis, Format, Generator, Interpolation, MapGeneratorConfig, Noise, Route, Storage, Viewshed};
+use contour::{ContourFormat, ContourOptions, LandmassOptions, draw_contours, write_components, write_geojson,
+              write_svg};
+use heightmap::{Contour, Diamond2d, Fractal2d, Generator2d, Heightmap, Midpoint2d, MmapHeightmap, Sample,
+                TileGenerator2d, heightmap_from_iter};
+use image::{Endianness, Export, GeoExport, GeoReference, NormalMap, RgbImage, Shadable, ToImage, overlay, read_f32,
+            read_png, read_r16};
 use interpolate;
-use noise2d::{Gradient2d, Simplex2d, Value2d};
+use mesh::{LodOptions, Mesh, Pyramid};
+use noise2d::{Gradient2d, Noise2d, Simplex2d, Value2d};
+use path::Terrain;
+use scatter::{Layers, Placement, ScatterOptions, draw_objects};
+use settlement::{Network, SettlementOptions};
+
+/// Random streams drawn besides the height map, keyed by the seed and their number so that none of
+/// them repeats another stream or the height map of another seed.
+const MOISTURE_STREAM: usize = 1;
+const SCATTER_STREAM: usize = 2;
+const FANTASY_STREAM: usize = 3;
 
 pub struct MapGenerator {
     config: MapGeneratorConfig,
 }
 
+/// Layers computed along the height map and drawn over the rendered map.
+struct Overlays {
+    biomes: Option<(BiomeMap, Heightmap)>,
+    objects: Option<Vec<Placement>>,
+    network: Option<Network>,
+    /// Share of the observers seeing each sample.
+    viewshed: Option<Heightmap>,
+    contours: Option<Vec<Contour>>,
+}
+
+impl Overlays {
+    fn is_empty(&self) -> bool {
+        self.biomes.is_none() && self.objects.is_none() && self.network.is_none() && self.viewshed.is_none() &&
+            self.contours.is_none()
+    }
+}
+
 impl MapGenerator {
     pub fn new(config: MapGeneratorConfig) -> MapGenerator {
         MapGenerator { config }
     }
 
-    pub fn run(&self) {
+    /// Generates the map, or reads it, and writes it. Maps failing the landmass constraints are
+    /// generated again from the next seeds.
+    pub fn run(mut self) -> io::Result<()> {
+        if let Some(ref input) = *self.config.input() {
+            let hmap = self.load(input)?;
+            return self.store(hmap);
+        }
+
+        if self.config.mmap().is_some() && *self.config.generator() != Generator::Fractal {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Memory-mapped height maps can only be generated by the fractal generator",
+            ));
+        }
+
+        let attempts = match self.config.landmass() {
+            Some(landmass) if landmass.constrained() && self.config.mmap().is_none() => landmass.attempts.max(1),
+            _ => 1,
+        };
+        for attempt in 0..attempts {
+            if attempt > 0 {
+                let seed = self.config.seed().wrapping_add(1);
+                self.config = self.config.set_seed(Some(seed));
+                println!("Seed used: {}", seed);
+            }
+            if self.generate_seeded()? {
+                return Ok(());
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("No seed met the landmass constraints in {} attempts", attempts),
+        ))
+    }
+
+    /// Whether the map generated from the seed of the config was kept.
+    fn generate_seeded(&self) -> io::Result<bool> {
         let seed: &[_] = &[*self.config.seed()];
         let mut r: StdRng = SeedableRng::from_seed(seed);
 
         match (self.config.generator(), self.config.noise().unwrap_or(Noise::Gradient)) {
             (&Generator::Diamond, _) => self.generate(Diamond2d::new(), &mut r),
-            (&Generator::Fractal, Noise::Value) => {
-                self.generate(
-                    Fractal2d::new(
-                        Value2d::new(
-                            &mut r,
-                            interpolate::get(self.config.interpolation().unwrap_or(Interpolation::Cubic)),
-                        ),
-                        self.config.scale().unwrap_or(2.0),
-                        self.config.octave().unwrap_or(10),
-                        self.config.lacunarity().unwrap_or(2.0),
-                        self.config.persistance().unwrap_or(0.5),
+            (&Generator::Fractal, Noise::Value) => self.generate_fractal(
+                Fractal2d::new(
+                    Value2d::new(
+                        &mut r,
+                        interpolate::get(self.config.interpolation().unwrap_or(Interpolation::Cubic)),
                     ),
-                    &mut r,
-                );
-            }
-            (&Generator::Fractal, Noise::Gradient) => self.generate(
+                    self.config.scale().unwrap_or(2.0),
+                    self.config.octave().unwrap_or(10),
+                    self.config.lacunarity().unwrap_or(2.0),
+                    self.config.persistance().unwrap_or(0.5),
+                ),
+                &mut r,
+            ),
+            (&Generator::Fractal, Noise::Gradient) => self.generate_fractal(
                 Fractal2d::new(
                     Gradient2d::new(
                         &mut r,
@@ -50,7 +121,7 @@ impl MapGenerator {
                 ),
                 &mut r,
             ),
-            (&Generator::Fractal, Noise::Simplex) => self.generate(
+            (&Generator::Fractal, Noise::Simplex) => self.generate_fractal(
                 Fractal2d::new(
                     Simplex2d::new(&mut r),
                     self.config.scale().unwrap_or(2.0),
@@ -61,27 +132,677 @@ impl MapGenerator {
                 &mut r,
             ),
             (&Generator::Midpoint, _) => self.generate(Midpoint2d::new(), &mut r),
+        }
+    }
+
+    fn generate_fractal<N, R>(&self, g: Fractal2d<N>, rng: &mut R) -> io::Result<bool>
+    where
+        N: Noise2d,
+        R: Rng,
+    {
+        match *self.config.mmap() {
+            Some(ref path) => {
+                match self.config.storage() {
+                    Storage::F64 => self.generate_mmap::<_, f64>(&g, path)?,
+                    Storage::F32 => self.generate_mmap::<_, f32>(&g, path)?,
+                    Storage::U16 => self.generate_mmap::<_, u16>(&g, path)?,
+                }
+                Ok(true)
+            }
+            None => self.generate(g, rng),
+        }
+    }
+
+    /// Generates the map tile by tile into the memory-mapped file and writes each tile.
+    fn generate_mmap<G, T>(&self, g: &G, path: &str) -> io::Result<()>
+    where
+        G: TileGenerator2d,
+        T: Sample,
+    {
+        let mut hmap = MmapHeightmap::<T>::create(
+            path,
+            *self.config.width(),
+            *self.config.height(),
+            self.config.tile_size().unwrap_or(1024),
+        )?;
+        hmap.generate(g);
+        hmap.flatten();
+
+        let (tiles_x, tiles_y) = hmap.tiles();
+        for ty in 0..tiles_y {
+            for tx in 0..tiles_x {
+                let file = tile_file(self.config.output(), tx, ty);
+
+                if self.config.format() == Format::Png {
+                    let (x, y, width, height) = hmap.tile_bounds(tx, ty);
+
+                    // Keep the samples around the tile that its shading depends on, so that it
+                    // matches across tiles
+                    let margin = self.tile_margin();
+                    let left = min(x, margin);
+                    let top = min(y, margin);
+                    let right = min(hmap.width() - x - width, margin);
+                    let bottom = min(hmap.height() - y - height, margin);
+                    let tile = hmap.submap(x - left, y - top, width + left + right, height + top + bottom);
+
+                    let img = self.colorize(&tile);
+                    let img = RgbImage::from_fn(width, height, |i, j| *img.get_pixel(i + left, j + top));
+                    img.save(file)?;
+                } else {
+                    let (x, y, _, height) = hmap.tile_bounds(tx, ty);
+                    let mut georef = *self.config.georeference();
+                    georef.origin_x += f64::from(x) * georef.cell_size;
+                    georef.origin_y += f64::from(hmap.height() - y - height) * georef.cell_size;
+
+                    self.save(&hmap.tile(tx, ty), file, &georef)?;
+                }
+            }
+        }
+
+        hmap.flush()
+    }
+
+    /// Samples on each side of a tile that its shading depends on: the neighbours of its normals,
+    /// and the reach of the shadows and of the occlusion of the hillshade.
+    fn tile_margin(&self) -> u32 {
+        let shading = match self.config.shading() {
+            Some(shading) => shading,
+            None => return 1,
         };
+
+        // A ray towards the light rises above any sample once it has climbed the whole height scale
+        let size = max(*self.config.width(), *self.config.height());
+        let shadow = if shading.shadow > 0.0 && shading.altitude < 90.0 {
+            min((shading.scale / shading.altitude.to_radians().tan()).ceil() as u32, size)
+        } else {
+            0
+        };
+        let occlusion = if shading.occlusion > 0.0 { shading.occlusion_radius } else { 0 };
+
+        max(1, max(shadow, occlusion))
     }
 
-    fn generate<G, R>(&self, g: G, rng: &mut R)
+    /// Whether the map met the landmass constraints and was written.
+    fn generate<G, R>(&self, g: G, rng: &mut R) -> io::Result<bool>
     where
         G: Generator2d,
         R: Rng,
     {
-        let file = &Path::new(self.config.output());
-        let mut hmap = g.generate(*self.config.width(), *self.config.height(), rng);
-        hmap.normalize();
+        match self.config.storage() {
+            Storage::F64 => self.generate_stored::<_, _, f64>(g, rng),
+            Storage::F32 => self.generate_stored::<_, _, f32>(g, rng),
+            Storage::U16 => self.generate_stored::<_, _, u16>(g, rng),
+        }
+    }
+
+    /// Generates the map straight into its storage type.
+    fn generate_stored<G, R, T>(&self, g: G, rng: &mut R) -> io::Result<bool>
+    where
+        G: Generator2d,
+        R: Rng,
+        T: Sample,
+    {
+        let mut hmap = g.generate::<T, _>(*self.config.width(), *self.config.height(), rng);
         hmap.flatten();
 
-        let mut img = self.config.ramp().apply_on(&hmap);
-        img.shade(
-            &hmap,
-            self.config.light_position(),
-            self.config.light(),
-            self.config.dark(),
+        match self.config.landmass() {
+            Some(landmass) if landmass.constrained() => {
+                let samples = hmap.width() as usize * hmap.height() as usize;
+                if let Err(reason) = landmass.check(&hmap.components(self.config.sea_level()).components, samples) {
+                    println!("Seed {} rejected: {}", self.config.seed(), reason);
+                    return Ok(false);
+                }
+            }
+            _ => {}
+        }
+
+        self.render(&hmap)?;
+        Ok(true)
+    }
+
+    fn load(&self, input: &str) -> io::Result<Heightmap> {
+        let file = BufReader::new(File::open(input)?);
+        let len = fs::metadata(input)?.len();
+
+        match self.config.input_format().unwrap_or(Format::Png) {
+            Format::Png | Format::Png16 => read_png(file),
+            Format::R16 => {
+                let (width, height) = self.raw_size(len / 2)?;
+                read_r16(file, width, height, Endianness::Little)
+            }
+            Format::R16be => {
+                let (width, height) = self.raw_size(len / 2)?;
+                read_r16(file, width, height, Endianness::Big)
+            }
+            Format::F32 => {
+                let (width, height) = self.raw_size(len / 4)?;
+                let mut hmap = read_f32(file, width, height)?;
+                hmap.normalize();
+                Ok(hmap)
+            }
+            Format::Asc | Format::GeoTiff => {
+                Err(io::Error::new(io::ErrorKind::InvalidInput, "Elevation models cannot be read"))
+            }
+            Format::Obj | Format::Stl | Format::Gltf | Format::Glb => {
+                Err(io::Error::new(io::ErrorKind::InvalidInput, "Meshes cannot be read"))
+            }
+            Format::NormalMap => Err(io::Error::new(io::ErrorKind::InvalidInput, "Normal maps cannot be read")),
+            Format::Svg => Err(io::Error::new(io::ErrorKind::InvalidInput, "Vector maps cannot be read")),
+            Format::Preview | Format::Isometric => {
+                Err(io::Error::new(io::ErrorKind::InvalidInput, "Previews cannot be read"))
+            }
+        }
+    }
+
+    /// Raw files have no header: use the configured size if it matches, or assume a square map.
+    fn raw_size(&self, samples: u64) -> io::Result<(u32, u32)> {
+        let (width, height) = (*self.config.width(), *self.config.height());
+        if u64::from(width) * u64::from(height) == samples {
+            return Ok((width, height));
+        }
+
+        let side = (samples as f64).sqrt().round() as u64;
+        if side * side == samples {
+            Ok((side as u32, side as u32))
+        } else {
+            Err(io::Error::new(io::ErrorKind::InvalidData, "Cannot guess the size of the raw height map"))
+        }
+    }
+
+    /// Renders a map read from a file in the storage type, the map as read being freed once converted.
+    fn store(&self, hmap: Heightmap) -> io::Result<()> {
+        match self.config.storage() {
+            Storage::F64 => self.render(&hmap),
+            Storage::F32 => self.render(&hmap.into_storage::<f32>()),
+            Storage::U16 => self.render(&hmap.into_storage::<u16>()),
+        }
+    }
+
+    /// Writes the map and its companion files, failing when any of them could not be written.
+    fn render<T>(&self, hmap: &Heightmap<T>) -> io::Result<()>
+    where
+        T: Sample,
+    {
+        if let Some(analysis) = self.config.analysis() {
+            return self.save_analysis(hmap, analysis);
+        }
+        if let Some(route) = self.config.route() {
+            return self.save_route(hmap, route);
+        }
+
+        if let Some(landmass) = self.config.landmass() {
+            self.save_landmasses(hmap, landmass)?;
+        }
+
+        // Biomes color the map and constrain the objects
+        let biomes = match self.config.biome() {
+            Some(biome) if self.config.format() == Format::Png || self.config.scatter().is_some() => {
+                Some(self.classify(hmap, biome)?)
+            }
+            _ => None,
+        };
+        let objects = match self.config.scatter() {
+            Some(scatter) => Some(self.save_objects(hmap, scatter, biomes.as_ref())?),
+            None => None,
+        };
+        let overlays = Overlays {
+            network: match self.config.settlement() {
+                Some(settlement) => Some(self.save_network(hmap, settlement)?),
+                None => None,
+            },
+            viewshed: match self.config.viewshed() {
+                Some(viewshed) => Some(self.save_viewshed(hmap, viewshed)?),
+                None => None,
+            },
+            contours: match self.config.contour() {
+                Some(contour) => Some(self.save_contours(hmap, contour)?),
+                None => None,
+            },
+            biomes,
+            objects,
+        };
+        if self.config.format() == Format::Png && !overlays.is_empty() {
+            return self.save_png(hmap, &overlays);
+        }
+
+        match (self.config.format(), self.config.lod()) {
+            (Format::Obj, Some(lod)) |
+            (Format::Stl, Some(lod)) |
+            (Format::Gltf, Some(lod)) |
+            (Format::Glb, Some(lod)) => self.save_pyramid(hmap, lod),
+            _ => self.save(hmap, self.config.output(), self.config.georeference()),
+        }
+    }
+
+    /// Biome and moisture of each sample, writing the id of the biome of each pixel in
+    /// `<output>_biomes.png`. A simulated climate also writes its temperature and precipitation as
+    /// 32-bit floats.
+    fn classify<T>(&self, hmap: &Heightmap<T>, biome: &BiomeOptions) -> io::Result<(BiomeMap, Heightmap)>
+    where
+        T: Sample,
+    {
+        let output = self.config.output();
+        let (temperature, moisture) = match biome.climate {
+            Some(ref climate) => {
+                let maps = climate.simulate(hmap, self.config.sea_level(), &biome.temperature);
+                maps.temperature
+                    .write_f32(BufWriter::new(File::create(companion_file(output, "temperature", "f32"))?))?;
+                maps.precipitation
+                    .write_f32(BufWriter::new(File::create(companion_file(output, "precipitation", "f32"))?))?;
+                (maps.temperature, maps.moisture)
+            }
+            None => (
+                biome.temperature.raster(hmap, self.config.sea_level()),
+                self.moisture(hmap.width(), hmap.height(), biome.moisture_scale),
+            ),
+        };
+        let biomes = biome.classify_map(hmap, self.config.sea_level(), &temperature, &moisture);
+
+        biomes.ids_image().save(companion_file(output, "biomes", "png"))?;
+
+        Ok((biomes, moisture))
+    }
+
+    /// Places the objects of the scatter rules and writes them in `<output>_objects.<json|csv>`.
+    fn save_objects<T>(
+        &self,
+        hmap: &Heightmap<T>,
+        scatter: &ScatterOptions,
+        biomes: Option<&(BiomeMap, Heightmap)>,
+    ) -> io::Result<Vec<Placement>>
+    where
+        T: Sample,
+    {
+        let georef = self.config.georeference();
+        let slope = hmap.slope(georef.vertical_scale / georef.cell_size);
+        let layers = Layers {
+            height: hmap,
+            slope: &slope,
+            moisture: biomes.map(|&(_, ref moisture)| moisture),
+            biomes: match (biomes, self.config.biome()) {
+                (Some(&(ref map, _)), Some(biome)) => Some((map, &biome.biomes[..])),
+                _ => None,
+            },
+        };
+
+        let objects = scatter.place(&layers, &mut self.stream(SCATTER_STREAM));
+
+        let file = companion_file(self.config.output(), "objects", scatter.export.extension());
+        scatter.write_objects(BufWriter::new(File::create(file)?), &objects)?;
+
+        Ok(objects)
+    }
+
+    /// Places the settlements and their roads and writes them in `<output>_settlements.geojson`.
+    fn save_network<T>(&self, hmap: &Heightmap<T>, settlement: &SettlementOptions) -> io::Result<Network>
+    where
+        T: Sample,
+    {
+        let georef = self.config.georeference();
+        let network = settlement.build(hmap, georef.vertical_scale / georef.cell_size, self.config.sea_level());
+
+        let file = companion_file(self.config.output(), "settlements", "geojson");
+        network.write_geojson(BufWriter::new(File::create(file)?), georef, hmap.height())?;
+
+        Ok(network)
+    }
+
+    /// Samples seen by the observers, from `0` for none to `1` for all, writing this share of the
+    /// observers as 8-bit gray levels in `<output>_viewshed.png`.
+    fn save_viewshed<T>(&self, hmap: &Heightmap<T>, viewshed: &Viewshed) -> io::Result<Heightmap>
+    where
+        T: Sample,
+    {
+        let observers = &viewshed.observers;
+        if observers.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "No observers"));
+        }
+        if let Some(&(x, y)) = observers.iter().find(|&&(x, y)| x >= hmap.width() || y >= hmap.height()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Observer at {},{} outside of the map", x, y),
+            ));
+        }
+
+        let georef = self.config.georeference();
+        let counts = hmap.cumulative_viewshed(observers, viewshed.height, georef.vertical_scale / georef.cell_size);
+        let shares = heightmap_from_iter(
+            hmap.width(),
+            hmap.height(),
+            counts.heights().map(|&c| c / observers.len() as f64),
+        );
+        shares.to_image().save(companion_file(self.config.output(), "viewshed", "png"))?;
+
+        Ok(shares)
+    }
+
+    /// Prints the number of islands and writes the components with their outlines in
+    /// `<output>_landmasses.geojson` if asked.
+    fn save_landmasses<T>(&self, hmap: &Heightmap<T>, landmass: &LandmassOptions) -> io::Result<()>
+    where
+        T: Sample,
+    {
+        let mut components = hmap.components(self.config.sea_level());
+        let islands: Vec<_> = components.components.iter().filter(|c| c.is_island()).collect();
+        let largest = islands.iter().map(|c| c.area).max().unwrap_or(0);
+        println!(
+            "Islands: {}, the largest covering {:.4} of the map",
+            islands.len(),
+            largest as f64 / (f64::from(hmap.width()) * f64::from(hmap.height()))
+        );
+
+        if landmass.export {
+            landmass.trace(hmap, &mut components);
+            let file = companion_file(self.config.output(), "landmasses", "geojson");
+            write_components(
+                BufWriter::new(File::create(file)?),
+                &components.components,
+                self.config.georeference(),
+                hmap.height(),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Traces the contours, writing them in `<output>_contours.<geojson|svg>` if asked.
+    fn save_contours<T>(&self, hmap: &Heightmap<T>, contour: &ContourOptions) -> io::Result<Vec<Contour>>
+    where
+        T: Sample,
+    {
+        let contours = contour.trace(hmap);
+        if let Some(export) = contour.export {
+            let georef = self.config.georeference();
+            let file = companion_file(self.config.output(), "contours", export.extension());
+            let file = BufWriter::new(File::create(file)?);
+            match export {
+                ContourFormat::GeoJson => write_geojson(file, &contours, contour, georef, hmap.height())?,
+                ContourFormat::Svg => write_svg(file, &contours, contour, georef, (hmap.width(), hmap.height()))?,
+            }
+        }
+
+        Ok(contours)
+    }
+
+    /// Writes the map colored by biome or by the ramp, or drawn on parchment with forests in their
+    /// biomes, with the overlays drawn over it.
+    fn save_png<T>(&self, hmap: &Heightmap<T>, overlays: &Overlays) -> io::Result<()>
+    where
+        T: Sample,
+    {
+        let mut img = match (&overlays.biomes, self.config.biome(), self.config.fantasy()) {
+            (&Some((ref map, _)), Some(biome), Some(fantasy)) => fantasy.render(
+                hmap,
+                self.config.sea_level(),
+                &self.fantasy_noise(),
+                Some(&map.mask(&biome.biomes, &fantasy.forests)),
+            ),
+            (&Some((ref map, _)), Some(biome), None) => self.shaded(map.colorize(&biome.biomes), hmap),
+            _ => self.draw(hmap),
+        };
+        if let (&Some(ref viewshed), Some(options)) = (&overlays.viewshed, self.config.viewshed()) {
+            overlay(&mut img, viewshed, options.color, options.opacity);
+        }
+        if let (&Some(ref contours), Some(contour)) = (&overlays.contours, self.config.contour()) {
+            if contour.draw {
+                draw_contours(&mut img, contours, contour);
+            }
+        }
+        if let (&Some(ref objects), Some(scatter)) = (&overlays.objects, self.config.scatter()) {
+            if scatter.draw {
+                draw_objects(&mut img, objects, &scatter.rules);
+            }
+        }
+        if let (&Some(ref network), Some(settlement)) = (&overlays.network, self.config.settlement()) {
+            if settlement.draw {
+                network.draw(&mut img, settlement);
+            }
+        }
+
+        img.save(self.config.output())
+    }
+
+    /// Writes the map colored by the ramp with the cheapest path of the route over it, and the
+    /// cost of reaching each sample in `<output>_distances.f32` if asked.
+    fn save_route<T>(&self, hmap: &Heightmap<T>, route: &Route) -> io::Result<()>
+    where
+        T: Sample,
+    {
+        let inside = |(x, y): (u32, u32)| x < hmap.width() && y < hmap.height();
+        if !inside(route.from) || !inside(route.to) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Path ends outside of the map"));
+        }
+
+        let georef = self.config.georeference();
+        let terrain = Terrain::new(
+            hmap,
+            georef.vertical_scale / georef.cell_size,
+            self.config.sea_level(),
+            &route.cost,
         );
+        if route.distances {
+            let file = companion_file(self.config.output(), "distances", "f32");
+            terrain
+                .distances(&[route.from])
+                .write_f32(BufWriter::new(File::create(file)?))?;
+        }
+
+        let path = terrain.path(route.from, route.to).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("No path from {:?} to {:?}", route.from, route.to))
+        })?;
+        println!("Path cost: {}", path.cost);
+
+        let mut img = self.colorize(hmap);
+        for &(x, y) in &path.points {
+            img.put_pixel(x, y, *route.color);
+        }
+
+        img.save(self.config.output())
+    }
+
+    /// Normalized noise, independent from the height map.
+    fn moisture(&self, width: u32, height: u32, scale: f64) -> Heightmap {
+        let mut r = self.stream(MOISTURE_STREAM);
+        let noise = Gradient2d::new(&mut r, interpolate::get(Interpolation::Cubic));
+        Fractal2d::new(noise, scale, 8, 2.0, 0.5).generate(width, height, &mut r)
+    }
+
+    /// Noise of the stains, hatches and glyphs of the maps drawn on parchment, varying with the seed.
+    fn fantasy_noise(&self) -> Value2d<fn(f64, f64, f64) -> f64> {
+        Value2d::new(&mut self.stream(FANTASY_STREAM), interpolate::get(Interpolation::Cubic))
+    }
 
-        let _ = img.save(file);
+    /// Generator of one of the random streams, seeded by both the seed of the config and `stream`.
+    fn stream(&self, stream: usize) -> StdRng {
+        let seed: &[_] = &[*self.config.seed(), stream];
+        SeedableRng::from_seed(seed)
     }
+
+    /// Writes the analysis raster: raw values for elevation models and 32-bit floats, mapped to
+    /// `[0, 1]` for the other formats.
+    fn save_analysis<T>(&self, hmap: &Heightmap<T>, analysis: Analysis) -> io::Result<()>
+    where
+        T: Sample,
+    {
+        let georef = self.config.georeference();
+        let scale = georef.vertical_scale / georef.cell_size;
+        let raster = match analysis {
+            Analysis::Slope => hmap.slope(scale),
+            Analysis::Aspect => hmap.aspect(scale),
+            Analysis::ProfileCurvature => hmap.profile_curvature(scale),
+            Analysis::PlanCurvature => hmap.plan_curvature(scale),
+            Analysis::Ruggedness => hmap.ruggedness(scale),
+        };
+
+        let output = self.config.output();
+        match self.config.format() {
+            Format::F32 | Format::Asc | Format::GeoTiff => {
+                let raw = GeoReference {
+                    vertical_scale: 1.0,
+                    vertical_offset: 0.0,
+                    ..*georef
+                };
+                self.save(&raster, output, &raw)
+            }
+            Format::Png => self.config.analysis_ramp().apply_on(&unit_range(analysis, &raster)).save(output),
+            _ => self.save(&unit_range(analysis, &raster), output, georef),
+        }
+    }
+
+    /// Writes every tile of the pyramid next to the output, and their index as a `.json` file.
+    fn save_pyramid<T>(&self, hmap: &Heightmap<T>, lod: &LodOptions) -> io::Result<()>
+    where
+        T: Sample,
+    {
+        let output = self.config.output();
+        let pyramid = Pyramid::new(hmap, self.config.mesh(), lod);
+        let format = self.config.format();
+        let img = match format {
+            Format::Gltf | Format::Glb => self.colorize(hmap),
+            _ => RgbImage::new(0, 0),
+        };
+
+        let mut tiles = Vec::new();
+        for level in 0..pyramid.levels() {
+            for (x, y) in pyramid.tiles(level) {
+                let file = lod_file(output, level, x, y);
+                let mesh = pyramid.tile(level, x, y)?;
+
+                match format {
+                    Format::Gltf | Format::Glb => mesh.write_gltf(&file, &pyramid.texture(&img, level, x, y))?,
+                    Format::Stl => mesh.write_stl(BufWriter::new(File::create(&file)?), None)?,
+                    _ => mesh.write_obj(BufWriter::new(File::create(&file)?))?,
+                }
+
+                let name = file.file_name().map_or(String::new(), |f| f.to_string_lossy().into_owned());
+                tiles.push(pyramid.entry(level, x, y, name, &mesh));
+            }
+        }
+
+        pyramid.write_index(BufWriter::new(File::create(Path::new(output).with_extension("json"))?), &tiles)
+    }
+
+    fn save<T, P>(&self, hmap: &Heightmap<T>, file: P, georef: &GeoReference) -> io::Result<()>
+    where
+        T: Sample,
+        P: AsRef<Path>,
+    {
+        match self.config.format() {
+            Format::Png => self.draw(hmap).save(file),
+            Format::Png16 => hmap.write_png16(BufWriter::new(File::create(file)?)),
+            Format::R16 => hmap.write_r16(BufWriter::new(File::create(file)?), Endianness::Little),
+            Format::R16be => hmap.write_r16(BufWriter::new(File::create(file)?), Endianness::Big),
+            Format::F32 => hmap.write_f32(BufWriter::new(File::create(file)?)),
+            Format::Asc => hmap.write_asc(BufWriter::new(File::create(file)?), georef),
+            Format::GeoTiff => hmap.write_geotiff(BufWriter::new(File::create(file)?), georef),
+            Format::Obj => Mesh::new(hmap, self.config.mesh())?.write_obj(BufWriter::new(File::create(file)?)),
+            Format::Stl => {
+                let options = self.config.mesh();
+                Mesh::new(hmap, options)?.write_stl(BufWriter::new(File::create(file)?), options.base)
+            }
+            Format::Gltf | Format::Glb => Mesh::new(hmap, self.config.mesh())?.write_gltf(file, &self.colorize(hmap)),
+            Format::NormalMap => hmap.normal_map(self.config.normal_map()).save(file),
+            Format::Svg => self.config.vector().write_svg(
+                BufWriter::new(File::create(file)?),
+                hmap,
+                self.config.sea_level(),
+                self.config.ramp(),
+                self.config.contour(),
+                georef,
+            ),
+            Format::Preview => {
+                self.config.preview().render(hmap, self.config.sea_level(), &self.colorize(hmap)).save(file)
+            }
+            Format::Isometric => {
+                self.config.isometric().render(hmap, self.config.sea_level(), self.config.ramp()).save(file)
+            }
+        }
+    }
+
+    /// The map drawn on parchment when set, colored by the ramp otherwise.
+    fn draw<T>(&self, hmap: &Heightmap<T>) -> RgbImage
+    where
+        T: Sample,
+    {
+        match self.config.fantasy() {
+            Some(fantasy) => fantasy.render(hmap, self.config.sea_level(), &self.fantasy_noise(), None),
+            None => self.colorize(hmap),
+        }
+    }
+
+    fn colorize<T>(&self, hmap: &Heightmap<T>) -> RgbImage
+    where
+        T: Sample,
+    {
+        self.shaded(self.config.ramp().apply_on(hmap), hmap)
+    }
+
+    fn shaded<T>(&self, mut img: RgbImage, hmap: &Heightmap<T>) -> RgbImage
+    where
+        T: Sample,
+    {
+        match self.config.shading() {
+            Some(shading) => img.hillshade(
+                hmap,
+                self.config.sea_level(),
+                shading,
+                self.config.light(),
+                self.config.dark(),
+            ),
+            None => img.shade(
+                hmap,
+                self.config.light_position(),
+                self.config.light(),
+                self.config.dark(),
+            ),
+        }
+
+        img
+    }
+}
+
+/// Maps an analysis to `[0, 1]`: angles by their range, curvatures keeping `0` in the middle.
+fn unit_range(analysis: Analysis, raster: &Heightmap) -> Heightmap {
+    let max = raster.heights().fold(0.0f64, |m, h| m.max(h.abs()));
+    let unit = |h: f64| match analysis {
+        Analysis::Slope => h / 90.0,
+        Analysis::Aspect => h.max(0.0) / 360.0,
+        Analysis::ProfileCurvature | Analysis::PlanCurvature if max > 0.0 => 0.5 + h / max / 2.0,
+        Analysis::Ruggedness if max > 0.0 => h / max,
+        _ => 0.5,
+    };
+
+    heightmap_from_iter(raster.width(), raster.height(), raster.heights().map(|&h| unit(h)))
+}
+
+/// Name of a file written along the output: `out.png` becomes `out_<suffix>.<extension>`.
+fn companion_file(output: &str, suffix: &str, extension: &str) -> PathBuf {
+    let path = Path::new(output);
+    let stem = path.file_stem().map_or("out".into(), |s| s.to_string_lossy());
+
+    path.with_file_name(format!("{}_{}.{}", stem, suffix, extension))
+}
+
+/// Name of a tile of a level of detail: `out.glb` becomes `out_<level>_<x>_<y>.glb`.
+fn lod_file(output: &str, level: u32, tx: u32, ty: u32) -> PathBuf {
+    let path = Path::new(output);
+    let stem = path.file_stem().map_or("out".into(), |s| s.to_string_lossy());
+    let name = match path.extension() {
+        Some(ext) => format!("{}_{}_{}_{}.{}", stem, level, tx, ty, ext.to_string_lossy()),
+        None => format!("{}_{}_{}_{}", stem, level, tx, ty),
+    };
+
+    path.with_file_name(name)
+}
+
+/// Name of the image of a tile: `out.png` becomes `out_<x>_<y>.png`.
+fn tile_file(output: &str, tx: u32, ty: u32) -> PathBuf {
+    let path = Path::new(output);
+    let stem = path.file_stem().map_or("out".into(), |s| s.to_string_lossy());
+    let name = match path.extension() {
+        Some(ext) => format!("{}_{}_{}.{}", stem, tx, ty, ext.to_string_lossy()),
+        None => format!("{}_{}_{}", stem, tx, ty),
+    };
+
+    path.with_file_name(name)
 }